@@ -1,43 +1,50 @@
 use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
-use wgpu_gol::LifeSimulation;
+use wgpu_gol::{Kernel, LifeSimulation, SimulationConfig};
 
 const FIXED_GRID_SIZE: u32 = 1024;
 
 fn benchmark(c: &mut Criterion) {
     // Create a random initialize state for the simulation.
     let num_cells = (FIXED_GRID_SIZE * FIXED_GRID_SIZE) as usize;
-    let mut init_state = vec![0; num_cells as usize];
-    for i in 0..num_cells {
-        init_state[i] = rand::random::<u8>() % 2;
+    let mut init_state = vec![0; num_cells];
+    for cell in &mut init_state {
+        *cell = rand::random::<u8>() % 2;
     }
 
-    let mut sim = pollster::block_on(LifeSimulation::new(FIXED_GRID_SIZE, &init_state));
+    let mut sim = pollster::block_on(LifeSimulation::new(
+        FIXED_GRID_SIZE,
+        FIXED_GRID_SIZE,
+        &init_state,
+    ));
 
     let mut group = c.benchmark_group("Simulate N Steps (1024x1024 Grid)");
-    for num_ticks in [1_000 /* 100, 10, 1 */] {
-        sim.reset_state(&init_state);
+    let num_ticks = 1_000 /* 100, 10, 1 */;
+    sim.reset_state(&init_state);
 
-        group.throughput(Throughput::Elements(num_ticks));
-        group.bench_with_input(BenchmarkId::from_parameter(num_ticks), &num_ticks, |b, size| {
+    group.throughput(Throughput::Elements(num_ticks));
+    group.bench_with_input(
+        BenchmarkId::from_parameter(num_ticks),
+        &num_ticks,
+        |b, size| {
             b.iter(|| {
                 simulate_n_steps(&mut sim, *size);
             });
-        });
-    }
+        },
+    );
     group.finish();
     drop(sim);
 
     let mut group = c.benchmark_group("Simulate NxN Grid (1,000 steps)");
     for size in [256, 512, 1024, 2048, 4096] {
         let num_cells = (size * size) as usize;
-        let mut init_state = vec![0; num_cells as usize];
-        for i in 0..num_cells {
-            init_state[i] = rand::random::<u8>() % 2;
+        let mut init_state = vec![0; num_cells];
+        for cell in &mut init_state {
+            *cell = rand::random::<u8>() % 2;
         }
 
         // TODO: Allow changing the grid size in `reset` so that we can reuse the same
         // simulation instance between benchmarks.
-        let mut sim = pollster::block_on(LifeSimulation::new(size, &init_state));
+        let mut sim = pollster::block_on(LifeSimulation::new(size, size, &init_state));
 
         group.throughput(Throughput::Elements((size * size * 1_000) as u64));
         group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _size| {
@@ -47,6 +54,36 @@ fn benchmark(c: &mut Criterion) {
         });
     }
     group.finish();
+
+    let mut group = c.benchmark_group("Compare Kernels (2048x2048 Grid, 1,000 steps)");
+    let size = 2048;
+    let init_state = (0..size * size)
+        .map(|_| rand::random::<u8>() % 2)
+        .collect::<Vec<_>>();
+    for kernel in [
+        Kernel::Direct,
+        Kernel::Tiled,
+        Kernel::Temporal { generations: 8 },
+    ] {
+        let config = SimulationConfig {
+            kernel,
+            ..Default::default()
+        };
+        let mut sim =
+            pollster::block_on(LifeSimulation::with_config(size, size, &init_state, config));
+
+        group.throughput(Throughput::Elements((size * size * 1_000) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{kernel:?}")),
+            &kernel,
+            |b, _kernel| {
+                b.iter(|| {
+                    simulate_n_steps(&mut sim, 1000);
+                });
+            },
+        );
+    }
+    group.finish();
 }
 
 fn simulate_n_steps(sim: &mut LifeSimulation, n: u64) {
@@ -58,9 +95,7 @@ fn simulate_n_steps(sim: &mut LifeSimulation, n: u64) {
             label: Some("Render Encoder"),
         });
 
-    for _ in 0..n {
-        sim.encode_compute_pass(&mut encoder);
-    }
+    sim.encode_steps(&mut encoder, n);
 
     sim.queue.submit([encoder.finish()]);
     sim.device
@@ -0,0 +1,269 @@
+use std::{borrow::Cow, fs::File, io, io::BufWriter, path::Path, time::Duration};
+
+use crate::{Image, LifeSimulation, Palette, Renderer};
+
+/// The file format of an [`Animation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AnimationFormat {
+    /// An animated GIF. Colors are limited to the three in the [`Palette`],
+    /// and alpha is ignored.
+    #[default]
+    Gif,
+
+    /// An animated PNG, which browsers and most image viewers support but
+    /// which shows the first frame in programs that only support PNGs.
+    Apng,
+}
+
+impl AnimationFormat {
+    /// Guesses the format from a file extension, without the leading dot.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Records a clip of a [`LifeSimulation`] running, rendered with
+/// [`Renderer`].
+///
+/// A frame is rendered for the current generation, and then again every
+/// `stride` generations until `generations` generations have been run. Each
+/// cell is drawn as a `scale` by `scale` square of pixels.
+///
+/// The fields can be set directly, or by chaining the builder methods, e.g.
+/// `Animation::new(100).stride(2).scale(8)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Animation {
+    /// The number of generations to run.
+    pub generations: u64,
+
+    /// The number of generations between frames. Defaults to 1.
+    pub stride: u64,
+
+    /// The size in pixels of each cell. Defaults to 4.
+    pub scale: u32,
+
+    /// How long each frame is shown for. Defaults to 100 ms. GIFs round this
+    /// down to a multiple of 10 ms.
+    pub frame_delay: Duration,
+
+    /// The colors of the cells.
+    pub palette: Palette,
+
+    /// The file format written by [`Self::record`]. Defaults to
+    /// [`AnimationFormat::Gif`].
+    pub format: AnimationFormat,
+}
+
+impl Animation {
+    /// Creates an animation of the next `generations` generations, with the
+    /// default settings.
+    pub fn new(generations: u64) -> Self {
+        Self {
+            generations,
+            stride: 1,
+            scale: 4,
+            frame_delay: Duration::from_millis(100),
+            palette: Palette::default(),
+            format: AnimationFormat::default(),
+        }
+    }
+
+    /// Sets the number of generations between frames.
+    pub fn stride(mut self, stride: u64) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    /// Sets the size in pixels of each cell.
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets how long each frame is shown for.
+    pub fn frame_delay(mut self, frame_delay: Duration) -> Self {
+        self.frame_delay = frame_delay;
+        self
+    }
+
+    /// Sets the colors of the cells.
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Sets the file format.
+    pub fn format(mut self, format: AnimationFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The number of frames in the animation, including the first one.
+    ///
+    /// Panics if the stride is 0.
+    pub fn frames(&self) -> u64 {
+        assert_ne!(self.stride, 0, "Animation stride must be at least 1");
+        self.generations / self.stride + 1
+    }
+
+    /// Runs `sim` and writes the animation to `writer`.
+    ///
+    /// The simulation is left at the generation of the last frame, which is
+    /// before the last `generations % stride` generations. Returns an error
+    /// with [`io::ErrorKind::InvalidInput`] if the frames are too large for
+    /// the format. Panics if the stride is 0.
+    pub fn record(&self, sim: &mut LifeSimulation, mut writer: impl io::Write) -> io::Result<()> {
+        let frames = self.frames();
+        let [width, height] = sim
+            .logical_grid_size
+            .map(|size| size.checked_mul(self.scale).filter(|&size| size > 0));
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(too_large());
+        };
+
+        let mut renderer = Renderer::offscreen(sim);
+        renderer.palette = self.palette;
+
+        let mut encoder = FrameEncoder::new(self, &mut writer, width, height, frames)?;
+        for frame in 0..frames {
+            if frame > 0 {
+                let mut command_encoder =
+                    sim.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Animation Encoder"),
+                        });
+                sim.encode_steps(&mut command_encoder, self.stride);
+                sim.queue.submit([command_encoder.finish()]);
+            }
+
+            encoder.write_frame(&renderer.render_image(sim, width, height))?;
+        }
+
+        encoder.finish()?;
+        writer.flush()
+    }
+
+    /// Runs `sim` and saves the animation as a file at `path`, see
+    /// [`Self::record`].
+    pub fn save(&self, sim: &mut LifeSimulation, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.record(sim, BufWriter::new(file))
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "animation frames are too large",
+    )
+}
+
+/// Writes frames in either of the supported formats.
+enum FrameEncoder<W: io::Write> {
+    Gif {
+        encoder: gif::Encoder<W>,
+        palette: Palette,
+        delay: u16,
+    },
+    Apng(png::Writer<W>),
+}
+
+impl<W: io::Write> FrameEncoder<W> {
+    fn new(
+        animation: &Animation,
+        writer: W,
+        width: u32,
+        height: u32,
+        frames: u64,
+    ) -> io::Result<Self> {
+        match animation.format {
+            AnimationFormat::Gif => {
+                let [Ok(width), Ok(height)] = [width, height].map(u16::try_from) else {
+                    return Err(too_large());
+                };
+
+                let palette = animation.palette;
+                let colors = [palette.alive, palette.dead, palette.background]
+                    .iter()
+                    .flat_map(|color| &color[..3])
+                    .copied()
+                    .collect::<Vec<_>>();
+                let mut encoder =
+                    gif::Encoder::new(writer, width, height, &colors).map_err(io::Error::other)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(io::Error::other)?;
+
+                // GIF delays are in hundredths of a second.
+                let delay = (animation.frame_delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+                Ok(Self::Gif {
+                    encoder,
+                    palette,
+                    delay,
+                })
+            }
+            AnimationFormat::Apng => {
+                let frames = u32::try_from(frames).map_err(|_| too_large())?;
+                let mut encoder = png::Encoder::new(writer, width, height);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+                encoder.set_animated(frames, 0)?;
+
+                let delay = animation.frame_delay.as_millis().min(u16::MAX as u128) as u16;
+                encoder.set_frame_delay(delay, 1000)?;
+                Ok(Self::Apng(encoder.write_header()?))
+            }
+        }
+    }
+
+    fn write_frame(&mut self, image: &Image) -> io::Result<()> {
+        match self {
+            Self::Gif {
+                encoder,
+                palette,
+                delay,
+            } => {
+                // The rendered colors match the palette exactly, but find the
+                // closest color anyway so that rounding can't break anything.
+                let colors = [palette.alive, palette.dead, palette.background];
+                let indices = image
+                    .pixels()
+                    .chunks(4)
+                    .map(|pixel| {
+                        let distance = |color: &[u8; 4]| {
+                            (0..3)
+                                .map(|i| (pixel[i] as i32 - color[i] as i32).pow(2))
+                                .sum::<i32>()
+                        };
+                        (0..colors.len())
+                            .min_by_key(|&i| distance(&colors[i]))
+                            .unwrap() as u8
+                    })
+                    .collect::<Vec<_>>();
+
+                let frame = gif::Frame {
+                    width: image.width() as u16,
+                    height: image.height() as u16,
+                    delay: *delay,
+                    buffer: Cow::Owned(indices),
+                    ..Default::default()
+                };
+                encoder.write_frame(&frame).map_err(io::Error::other)
+            }
+            Self::Apng(writer) => Ok(writer.write_image_data(image.pixels())?),
+        }
+    }
+
+    /// Writes the end of the file.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Gif { encoder, .. } => encoder.into_inner().map(drop),
+            Self::Apng(writer) => Ok(writer.finish()?),
+        }
+    }
+}
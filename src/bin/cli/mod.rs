@@ -0,0 +1,141 @@
+//! Command line options shared by the binaries.
+
+#![allow(dead_code)]
+
+use std::{error::Error, path::PathBuf};
+
+use rand::{SeedableRng, rngs::StdRng};
+use wgpu_gol::{PackedGrid, Pattern, Rule, SimulationConfig, Topology};
+
+/// The size of the grid used for random soups when no size is given.
+pub const DEFAULT_GRID_SIZE: u32 = 64;
+
+/// Options for the starting state of the simulation and the device it runs on.
+#[derive(Debug, clap::Args)]
+pub struct SimArgs {
+    /// Pattern file to start from, in any format supported by `Pattern::parse`
+    /// (RLE, plaintext, Life 1.05/1.06 or macrocell). A random soup is used if
+    /// this is left out.
+    pub pattern: Option<PathBuf>,
+
+    /// Width of the grid. Defaults to the width of the pattern, or 64 for
+    /// soups.
+    #[arg(long)]
+    pub width: Option<u32>,
+
+    /// Height of the grid. Defaults to the height of the pattern, or 64 for
+    /// soups.
+    #[arg(long)]
+    pub height: Option<u32>,
+
+    /// Fraction of cells alive in a random soup.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_density)]
+    pub density: f64,
+
+    /// Seed for the random soup. A random seed is used if this is left out.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Rule to run, e.g. `B36/S23`. Defaults to the pattern's rule, or
+    /// Conway's game of life.
+    #[arg(long)]
+    pub rule: Option<Rule>,
+
+    /// What happens at the edges of the grid: `torus`, `plane`,
+    /// `klein-bottle-horizontal`, `klein-bottle-vertical` or `cross-surface`.
+    #[arg(long, default_value_t = Topology::Torus)]
+    pub topology: Topology,
+
+    /// Comma separated list of backends to pick the adapter from, e.g.
+    /// `vulkan` or `metal,gl`. Defaults to all backends, or the
+    /// `WGPU_BACKEND` environment variable.
+    #[arg(long, value_parser = parse_backends)]
+    pub backend: Option<wgpu::Backends>,
+}
+
+impl SimArgs {
+    /// Builds the starting grid, with the pattern centered in it, and the
+    /// config for the simulation.
+    pub fn load(&self) -> Result<(PackedGrid, SimulationConfig), Box<dyn Error>> {
+        let (grid, rule) = match &self.pattern {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+                let pattern = Pattern::parse(&contents)
+                    .map_err(|err| format!("failed to parse {}: {err}", path.display()))?;
+
+                let width = self.width.unwrap_or(pattern.width());
+                let height = self.height.unwrap_or(pattern.height());
+                if width < pattern.width() || height < pattern.height() {
+                    return Err(format!(
+                        "the grid must be at least {}x{} to fit the pattern",
+                        pattern.width(),
+                        pattern.height(),
+                    )
+                    .into());
+                }
+
+                let mut grid = PackedGrid::new(width, height);
+                let x = (width - pattern.width()) / 2;
+                let y = (height - pattern.height()) / 2;
+                grid.paste(x, y, &pattern.grid);
+                (grid, pattern.rule)
+            }
+            None => {
+                let mut rng = match self.seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_os_rng(),
+                };
+                let width = self.width.unwrap_or(DEFAULT_GRID_SIZE);
+                let height = self.height.unwrap_or(DEFAULT_GRID_SIZE);
+                let grid = PackedGrid::random(width, height, self.density, &mut rng);
+                (grid, None)
+            }
+        };
+
+        let mut config = SimulationConfig::new()
+            .rule(self.rule.or(rule).unwrap_or_default())
+            .topology(self.topology)
+            .with_env();
+        if let Some(backends) = self.backend {
+            config.backends = backends;
+        }
+
+        Ok((grid, config))
+    }
+}
+
+/// Parses a color written as `rrggbb` or `rrggbbaa` hex, optionally starting
+/// with a `#`.
+pub fn parse_color(s: &str) -> Result<[u8; 4], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return Err(format!("expected a color like ff8000, got {s:?}"));
+    }
+
+    let mut color = [255; 4];
+    for (channel, digits) in color.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let digits = std::str::from_utf8(digits).unwrap();
+        *channel = u8::from_str_radix(digits, 16).map_err(|err| err.to_string())?;
+    }
+
+    Ok(color)
+}
+
+fn parse_density(s: &str) -> Result<f64, String> {
+    match s.parse() {
+        Ok(density) if (0.0..=1.0).contains(&density) => Ok(density),
+        _ => Err(format!("expected a density between 0 and 1, got {s:?}")),
+    }
+}
+
+fn parse_backends(s: &str) -> Result<wgpu::Backends, String> {
+    let backends = wgpu::Backends::from_comma_list(s);
+    if backends.is_empty() {
+        return Err(format!(
+            "unknown backend {s:?}, expected vulkan, metal, dx12 or gl"
+        ));
+    }
+
+    Ok(backends)
+}
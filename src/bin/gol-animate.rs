@@ -0,0 +1,93 @@
+//! Records an animated GIF or APNG of a pattern or random soup evolving.
+//!
+//! ```text
+//! cargo run --bin gol-animate -- glider_gun.rle --width 64 --height 48 \
+//!     --generations 120 --stride 2 --scale 6 -o glider_gun.gif
+//! ```
+
+use std::{error::Error, path::PathBuf, time::Duration};
+
+use clap::Parser;
+use wgpu_gol::{Animation, AnimationFormat, LifeSimulation, Palette};
+
+mod cli;
+
+#[derive(Debug, Parser)]
+#[command(about = "Records an animation of a Game of Life run")]
+struct Args {
+    #[command(flatten)]
+    sim: cli::SimArgs,
+
+    /// Where to save the animation. The format is picked from the extension
+    /// unless `--format` is given.
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Number of generations to run.
+    #[arg(short, long, default_value_t = 100)]
+    generations: u64,
+
+    /// Number of generations between frames.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    stride: u64,
+
+    /// Size of each cell in pixels.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+
+    /// How long each frame is shown for, in milliseconds.
+    #[arg(long, default_value_t = 100)]
+    delay_ms: u64,
+
+    /// Color of live cells, as `rrggbb` or `rrggbbaa` hex.
+    #[arg(long, value_parser = cli::parse_color)]
+    alive: Option<[u8; 4]>,
+
+    /// Color of dead cells, as `rrggbb` or `rrggbbaa` hex.
+    #[arg(long, value_parser = cli::parse_color)]
+    dead: Option<[u8; 4]>,
+
+    /// File format, either `gif` or `apng`.
+    #[arg(long, value_parser = parse_format)]
+    format: Option<AnimationFormat>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let format = match args.format {
+        Some(format) => format,
+        None => args
+            .output
+            .extension()
+            .and_then(|extension| AnimationFormat::from_extension(&extension.to_string_lossy()))
+            .ok_or("can't tell the format from the output path, use --format")?,
+    };
+
+    let mut palette = Palette::default();
+    palette.alive = args.alive.unwrap_or(palette.alive);
+    palette.dead = args.dead.unwrap_or(palette.dead);
+
+    let (grid, config) = args.sim.load()?;
+    let mut sim = pollster::block_on(LifeSimulation::try_from_grid(&grid, config))?;
+
+    let animation = Animation::new(args.generations)
+        .stride(args.stride)
+        .scale(args.scale)
+        .frame_delay(Duration::from_millis(args.delay_ms))
+        .palette(palette)
+        .format(format);
+    animation.save(&mut sim, &args.output)?;
+
+    println!(
+        "Wrote {} frames to {}",
+        animation.frames(),
+        args.output.display(),
+    );
+    Ok(())
+}
+
+fn parse_format(s: &str) -> Result<AnimationFormat, String> {
+    AnimationFormat::from_extension(s).ok_or_else(|| format!("unknown format {s:?}"))
+}
@@ -0,0 +1,320 @@
+//! Runs a pattern or random soup for a number of generations without opening a
+//! window, and saves the final state.
+//!
+//! ```text
+//! cargo run --release --bin gol-run -- --width 4096 --height 4096 --seed 1 \
+//!     --generations 100000 --kernel temporal -o final.rle \
+//!     --snapshot-every 10000 --snapshot-dir snapshots \
+//!     --population-csv population.csv --population-every 100
+//! ```
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use clap::Parser;
+use wgpu_gol::{
+    EncodedReadback, GridStats, Kernel, LifeSimulation, PatternFormat, StateReadback, StatsOptions,
+};
+
+mod cli;
+
+/// The most population reads left in flight before waiting for them. Each
+/// read has its own small staging buffer.
+const MAX_PENDING_READS: usize = 4096;
+
+#[derive(Debug, Parser)]
+#[command(about = "Runs Game of Life on the GPU without a window")]
+struct Args {
+    #[command(flatten)]
+    sim: cli::SimArgs,
+
+    /// Number of generations to run.
+    #[arg(short, long)]
+    generations: u64,
+
+    /// Compute kernel: `direct`, `tiled`, or `temporal` optionally followed by
+    /// the generations per pass, e.g. `temporal:8`.
+    #[arg(long, default_value = "direct", value_parser = parse_kernel)]
+    kernel: Kernel,
+
+    /// Most generations to encode before submitting them to the GPU.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    batch: u64,
+
+    /// Where to save the final state. The format is picked from the extension,
+    /// and defaults to RLE.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Number of generations between snapshots of the whole grid.
+    #[arg(long, requires = "snapshot_dir", value_parser = clap::value_parser!(u64).range(1..))]
+    snapshot_every: Option<u64>,
+
+    /// Directory to save snapshots in, named like `gen-000100.rle`.
+    #[arg(long, requires = "snapshot_every")]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Format of the snapshots: `rle`, `cells`, `lif` or `mc`.
+    #[arg(long, default_value = "rle", value_parser = parse_pattern_format)]
+    snapshot_format: PatternFormat,
+
+    /// Where to save the population over time, as a CSV file with
+    /// `generation,population` rows.
+    #[arg(long)]
+    population_csv: Option<PathBuf>,
+
+    /// Number of generations between rows of the population CSV.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    population_every: u64,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let output_format = match &args.output {
+        Some(path) => path
+            .extension()
+            .map(|extension| {
+                PatternFormat::from_extension(&extension.to_string_lossy())
+                    .ok_or("can't tell the format from the output path")
+            })
+            .transpose()?
+            .unwrap_or(PatternFormat::Rle),
+        None => PatternFormat::Rle,
+    };
+
+    if let Some(dir) = &args.snapshot_dir {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+    }
+
+    let mut population_csv = args
+        .population_csv
+        .as_ref()
+        .map(|path| -> Result<_, Box<dyn Error>> {
+            let file = File::create(path)
+                .map_err(|err| format!("failed to create {}: {err}", path.display()))?;
+            let mut writer = BufWriter::new(file);
+            writeln!(writer, "generation,population")?;
+            Ok(Population {
+                writer,
+                encoded: Vec::new(),
+                pending: Vec::new(),
+            })
+        })
+        .transpose()?;
+
+    let (grid, config) = args.sim.load()?;
+    let config = config.kernel(args.kernel);
+    let mut sim = pollster::block_on(LifeSimulation::try_from_grid(&grid, config))?;
+
+    let start = Instant::now();
+    let mut generation = 0;
+    save_snapshot(&args, &mut sim, generation)?;
+    loop {
+        let batch_start = generation;
+        let mut encoder = sim
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Batch Encoder"),
+            });
+
+        if let Some(population) = &mut population_csv
+            && generation == 0
+        {
+            population.sample(&mut sim, &mut encoder, generation);
+        }
+
+        // Snapshots read the whole grid back, so batches end at them.
+        let mut end = (generation + args.batch).min(args.generations);
+        if let Some(every) = args.snapshot_every {
+            end = end.min(next_multiple(generation, every));
+        }
+
+        // Population samples are recorded into the batch in between the
+        // steps, so they don't split it up.
+        while generation < end {
+            let mut next = end;
+            if population_csv.is_some() {
+                next = next.min(next_multiple(generation, args.population_every));
+            }
+
+            sim.encode_steps(&mut encoder, next - generation);
+            generation = next;
+
+            if let Some(population) = &mut population_csv
+                && generation.is_multiple_of(args.population_every)
+            {
+                population.sample(&mut sim, &mut encoder, generation);
+            }
+        }
+
+        sim.queue.submit([encoder.finish()]);
+        if let Some(population) = &mut population_csv {
+            population.submitted(&sim)?;
+        }
+
+        // The first snapshot was saved before the loop, so it isn't saved
+        // again if there was nothing to run.
+        if generation > batch_start {
+            save_snapshot(&args, &mut sim, generation)?;
+        }
+        if generation == args.generations {
+            break;
+        }
+    }
+
+    if let Some(population) = &mut population_csv {
+        population.finish(&sim)?;
+    }
+
+    if let Some(path) = &args.output {
+        save_pattern(&mut sim, path, output_format)?;
+    } else {
+        // Still wait for the GPU so that the timing below is meaningful.
+        sim.device.poll(wgpu::PollType::Wait)?;
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "Ran {} generations in {:.2?} ({:.0} generations/s), final population {}",
+        args.generations,
+        elapsed,
+        args.generations as f64 / elapsed.as_secs_f64(),
+        sim.population(),
+    );
+    Ok(())
+}
+
+/// The population CSV, and the reads that haven't been written to it yet.
+struct Population {
+    writer: BufWriter<File>,
+
+    /// Reads recorded into the batch that hasn't been submitted yet.
+    encoded: Vec<(u64, EncodedReadback<GridStats>)>,
+
+    /// Reads that have been submitted, in order of generation.
+    pending: Vec<(u64, StateReadback<GridStats>)>,
+}
+
+impl Population {
+    /// Records reading the population at `generation` into the batch.
+    fn sample(
+        &mut self,
+        sim: &mut LifeSimulation,
+        encoder: &mut wgpu::CommandEncoder,
+        generation: u64,
+    ) {
+        let readback = sim.encode_stats(encoder, StatsOptions::new());
+        self.encoded.push((generation, readback));
+    }
+
+    /// Starts the reads recorded into the batch that was just submitted,
+    /// writing out earlier reads if too many are in flight.
+    fn submitted(&mut self, sim: &LifeSimulation) -> Result<(), Box<dyn Error>> {
+        self.pending.extend(
+            self.encoded
+                .drain(..)
+                .map(|(generation, readback)| (generation, readback.submitted())),
+        );
+
+        if self.pending.len() >= MAX_PENDING_READS {
+            self.finish(sim)?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the reads in flight and writes them out.
+    fn finish(&mut self, sim: &LifeSimulation) -> Result<(), Box<dyn Error>> {
+        sim.device.poll(wgpu::PollType::Wait)?;
+        for (generation, readback) in self.pending.drain(..) {
+            let stats = pollster::block_on(readback)?;
+            writeln!(self.writer, "{generation},{}", stats.population)?;
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Saves a snapshot of the grid if one is due at `generation`.
+fn save_snapshot(
+    args: &Args,
+    sim: &mut LifeSimulation,
+    generation: u64,
+) -> Result<(), Box<dyn Error>> {
+    if let (Some(every), Some(dir)) = (args.snapshot_every, &args.snapshot_dir)
+        && generation.is_multiple_of(every)
+    {
+        let extension = extension(args.snapshot_format);
+        let path = dir.join(format!("gen-{generation:06}.{extension}"));
+        save_pattern(sim, &path, args.snapshot_format)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the smallest multiple of `every` after `generation`.
+fn next_multiple(generation: u64, every: u64) -> u64 {
+    (generation / every + 1) * every
+}
+
+fn save_pattern(
+    sim: &mut LifeSimulation,
+    path: &Path,
+    format: PatternFormat,
+) -> Result<(), Box<dyn Error>> {
+    let pattern = sim.read_pattern();
+    std::fs::write(path, pattern.write(format))
+        .map_err(|err| format!("failed to write {}: {err}", path.display()).into())
+}
+
+fn extension(format: PatternFormat) -> &'static str {
+    match format {
+        PatternFormat::Rle => "rle",
+        PatternFormat::Plaintext => "cells",
+        PatternFormat::Life105 | PatternFormat::Life106 => "lif",
+        PatternFormat::Macrocell => "mc",
+    }
+}
+
+fn parse_pattern_format(s: &str) -> Result<PatternFormat, String> {
+    PatternFormat::from_extension(s).ok_or_else(|| format!("unknown pattern format {s:?}"))
+}
+
+fn parse_kernel(s: &str) -> Result<Kernel, String> {
+    let (name, generations) = match s.split_once(':') {
+        Some((name, generations)) => (name, Some(generations)),
+        None => (s, None),
+    };
+
+    match (name.to_ascii_lowercase().as_str(), generations) {
+        ("direct", None) => Ok(Kernel::Direct),
+        ("tiled", None) => Ok(Kernel::Tiled),
+        ("temporal", generations) => {
+            let generations = match generations {
+                Some(generations) => generations
+                    .parse()
+                    .ok()
+                    .filter(|generations| (1..=Kernel::MAX_GENERATIONS).contains(generations))
+                    .ok_or_else(|| {
+                        format!(
+                            "expected between 1 and {} generations per pass, got {generations:?}",
+                            Kernel::MAX_GENERATIONS,
+                        )
+                    })?,
+                None => Kernel::MAX_GENERATIONS,
+            };
+            Ok(Kernel::Temporal { generations })
+        }
+        _ => Err(format!(
+            "unknown kernel {s:?}, expected direct, tiled or temporal"
+        )),
+    }
+}
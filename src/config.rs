@@ -0,0 +1,233 @@
+use crate::{Rule, SimError, Topology};
+
+/// Options controlling the behavior of a [`LifeSimulation`] and the GPU it
+/// runs on.
+///
+/// The fields can be set directly, or by chaining the builder methods, e.g.
+/// `SimulationConfig::new().kernel(Kernel::Tiled).force_fallback_adapter(true)`.
+///
+/// [`LifeSimulation`]: crate::LifeSimulation
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// The rule used to advance the simulation. Defaults to Conway's game of
+    /// life.
+    pub rule: Rule,
+
+    /// What happens at the edges of the grid. Defaults to a torus.
+    pub topology: Topology,
+
+    /// The compute kernel used to advance the simulation. Defaults to
+    /// [`Kernel::Direct`].
+    pub kernel: Kernel,
+
+    /// The backends the adapter may be picked from. Defaults to all backends.
+    pub backends: wgpu::Backends,
+
+    /// Which adapter to prefer when several are available. Defaults to
+    /// [`wgpu::PowerPreference::HighPerformance`].
+    pub power_preference: wgpu::PowerPreference,
+
+    /// Only use a fallback adapter, i.e. a software implementation such as
+    /// lavapipe or llvmpipe. Defaults to `false`.
+    pub force_fallback_adapter: bool,
+
+    /// Features the device must support. Defaults to none.
+    pub required_features: wgpu::Features,
+
+    /// Limits the device must support. Defaults to the WebGPU defaults, which
+    /// allow state buffers of up to 128 MiB.
+    pub required_limits: wgpu::Limits,
+}
+
+impl SimulationConfig {
+    /// Creates the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the backends, power preference and fallback adapter option
+    /// with the `WGPU_BACKEND`, `WGPU_POWER_PREF` and
+    /// `WGPU_FORCE_FALLBACK_ADAPTER` environment variables, if they are set.
+    pub fn with_env(mut self) -> Self {
+        self.backends = self.backends.with_env();
+        self.power_preference = wgpu::PowerPreference::from_env().unwrap_or(self.power_preference);
+        if let Ok(value) = std::env::var("WGPU_FORCE_FALLBACK_ADAPTER") {
+            self.force_fallback_adapter = matches!(value.to_lowercase().as_str(), "1" | "true");
+        }
+        self
+    }
+
+    /// Sets the rule used to advance the simulation.
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    /// Sets what happens at the edges of the grid.
+    pub fn topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Sets the compute kernel used to advance the simulation.
+    pub fn kernel(mut self, kernel: Kernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    /// Sets the backends the adapter may be picked from.
+    pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// Sets which adapter to prefer when several are available.
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Sets whether to only use a fallback (software) adapter.
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    /// Sets the features the device must support.
+    pub fn required_features(mut self, required_features: wgpu::Features) -> Self {
+        self.required_features = required_features;
+        self
+    }
+
+    /// Sets the limits the device must support.
+    pub fn required_limits(mut self, required_limits: wgpu::Limits) -> Self {
+        self.required_limits = required_limits;
+        self
+    }
+
+    /// Requests a device using the adapter and device options, as done by
+    /// [`LifeSimulation::try_new`].
+    ///
+    /// This is useful for sharing one device between several simulations, see
+    /// [`LifeSimulation::from_device`].
+    ///
+    /// [`LifeSimulation::try_new`]: crate::LifeSimulation::try_new
+    /// [`LifeSimulation::from_device`]: crate::LifeSimulation::from_device
+    pub async fn request_device(&self) -> Result<(wgpu::Device, wgpu::Queue), SimError> {
+        let (_, adapter) = self.request_adapter().await?;
+        self.request_device_from(&adapter).await
+    }
+
+    /// Picks an adapter using the adapter options.
+    pub(crate) async fn request_adapter(
+        &self,
+    ) -> Result<(wgpu::Instance, wgpu::Adapter), SimError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: self.power_preference,
+                force_fallback_adapter: self.force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+            .map_err(SimError::NoAdapter)?;
+
+        let info = adapter.get_info();
+        log::info!(
+            "Using adapter {:?} ({:?}, {:?})",
+            info.name,
+            info.backend,
+            info.device_type,
+        );
+
+        Ok((instance, adapter))
+    }
+
+    /// Requests a device from `adapter` using the device options.
+    pub(crate) async fn request_device_from(
+        &self,
+        adapter: &wgpu::Adapter,
+    ) -> Result<(wgpu::Device, wgpu::Queue), SimError> {
+        adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("Simulation Device"),
+                required_features: self.required_features,
+                required_limits: self.required_limits.clone(),
+                ..Default::default()
+            })
+            .await
+            .map_err(SimError::RequestDevice)
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            rule: Rule::default(),
+            topology: Topology::default(),
+            kernel: Kernel::default(),
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+        }
+    }
+}
+
+/// The compute shader used to advance the simulation.
+///
+/// Both kernels produce identical results, they only differ in performance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kernel {
+    /// Each invocation loads the blocks around it directly from the state
+    /// buffer.
+    #[default]
+    Direct,
+
+    /// Each workgroup loads a 2D tile of blocks plus a one block halo into
+    /// workgroup memory, and invocations read their neighbors from there.
+    Tiled,
+
+    /// Like [`Kernel::Tiled`], but each workgroup advances its tile several
+    /// generations in workgroup memory before writing it back, which cuts the
+    /// traffic to the state buffers by about that many times.
+    ///
+    /// Each compute pass advances the simulation by `generations`, see
+    /// [`LifeSimulation::encode_steps`] for running an arbitrary number of
+    /// generations.
+    ///
+    /// [`LifeSimulation::encode_steps`]: crate::LifeSimulation::encode_steps
+    Temporal {
+        /// The number of generations computed by each compute pass, from 1 to
+        /// [`Kernel::MAX_GENERATIONS`].
+        generations: u32,
+    },
+}
+
+impl Kernel {
+    /// The maximum number of generations [`Kernel::Temporal`] can compute in
+    /// one compute pass. Must match `MAX_GENERATIONS` in the shader.
+    pub const MAX_GENERATIONS: u32 = 16;
+
+    /// The number of generations the kernel advances the simulation by in
+    /// each compute pass.
+    pub fn generations_per_pass(&self) -> u32 {
+        match *self {
+            Kernel::Direct | Kernel::Tiled => 1,
+            Kernel::Temporal { generations } => generations,
+        }
+    }
+
+    /// The name of the entry point in the shader for the kernel.
+    pub(crate) fn entry_point(&self) -> &'static str {
+        match self {
+            Kernel::Direct => "compute_main",
+            Kernel::Tiled => "compute_tiled",
+            Kernel::Temporal { .. } => "compute_temporal",
+        }
+    }
+}
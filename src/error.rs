@@ -0,0 +1,85 @@
+use std::fmt;
+
+/// The error returned when creating a [`LifeSimulation`] fails, or when
+/// reloading its shader fails.
+///
+/// [`LifeSimulation`]: crate::LifeSimulation
+#[derive(Debug)]
+pub enum SimError {
+    /// The grid has a width or height of zero, or has more cells than can be
+    /// indexed with a `u32`.
+    InvalidDimensions { width: u32, height: u32 },
+
+    /// The initial state doesn't have exactly one byte per cell.
+    InvalidStateLength { expected: usize, actual: usize },
+
+    /// [`Kernel::Temporal`] was asked to run a number of generations per pass
+    /// outside of `1..=Kernel::MAX_GENERATIONS`.
+    ///
+    /// [`Kernel::Temporal`]: crate::Kernel::Temporal
+    InvalidGenerations(u32),
+
+    /// No adapter matching the requested options is available.
+    NoAdapter(wgpu::RequestAdapterError),
+
+    /// The adapter couldn't provide a device.
+    RequestDevice(wgpu::RequestDeviceError),
+
+    /// The grid is too large for one of the device's limits.
+    LimitExceeded {
+        /// The name of the limit, as it appears in [`wgpu::Limits`].
+        limit: &'static str,
+        requested: u64,
+        max: u64,
+    },
+
+    /// The shader file couldn't be read when hot reloading it.
+    ReadShader(std::io::Error),
+
+    /// The shader failed to compile, or the pipelines using it failed
+    /// validation.
+    Shader(wgpu::Error),
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidDimensions { width, height } => {
+                write!(f, "invalid grid dimensions {width}x{height}")
+            }
+            Self::InvalidStateLength { expected, actual } => write!(
+                f,
+                "initial state has wrong size, expected {expected} but got {actual}",
+            ),
+            Self::InvalidGenerations(generations) => write!(
+                f,
+                "generations per pass must be between 1 and {}, got {generations}",
+                crate::Kernel::MAX_GENERATIONS,
+            ),
+            Self::NoAdapter(err) => write!(f, "no suitable adapter found: {err}"),
+            Self::RequestDevice(err) => write!(f, "failed to request device: {err}"),
+            Self::LimitExceeded {
+                limit,
+                requested,
+                max,
+            } => write!(
+                f,
+                "grid needs {requested} for {limit}, but the device only supports {max}",
+            ),
+            Self::ReadShader(err) => write!(f, "failed to read shader file: {err}"),
+            Self::Shader(err) => write!(f, "failed to create simulation pipeline: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SimError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoAdapter(err) => Some(err),
+            Self::RequestDevice(err) => Some(err),
+            Self::ReadShader(err) => Some(err),
+            Self::Shader(err) => Some(err),
+            _ => None,
+        }
+    }
+}
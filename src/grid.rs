@@ -0,0 +1,230 @@
+use rand::Rng;
+
+use crate::{Bounds, pack_grid, unpack_grid};
+
+/// A grid of cells packed into `u32` blocks, in the same layout as the state
+/// buffers on the GPU.
+///
+/// Each row of the grid is split into blocks of 32 cells, with bit `i` of a
+/// block holding the cell `i` cells to the right of the start of the block.
+/// The last block of each row is padded with dead cells if the width isn't a
+/// multiple of 32.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackedGrid {
+    size: [u32; 2],
+    blocks: Vec<u32>,
+}
+
+impl PackedGrid {
+    /// Creates a `width` by `height` grid with all cells dead.
+    pub fn new(width: u32, height: u32) -> Self {
+        let blocks = vec![0; width.div_ceil(32) as usize * height as usize];
+        Self {
+            size: [width, height],
+            blocks,
+        }
+    }
+
+    /// Creates a `width` by `height` grid from one byte per cell in row-major
+    /// order, with any non-zero value marking a live cell.
+    ///
+    /// Panics if `cells` has the wrong length.
+    pub fn from_cells(width: u32, height: u32, cells: &[u8]) -> Self {
+        let (blocks, _) = pack_grid([width, height], cells);
+        Self {
+            size: [width, height],
+            blocks,
+        }
+    }
+
+    /// Creates a `width` by `height` random soup, with each cell alive with
+    /// probability `density`.
+    ///
+    /// Panics if `density` isn't between 0 and 1.
+    pub fn random(width: u32, height: u32, density: f64, rng: &mut impl Rng) -> Self {
+        let mut grid = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if rng.random_bool(density) {
+                    grid.set(x, y, true);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Creates a `width` by `height` grid from blocks in the layout used on
+    /// the GPU, e.g. the contents of a state buffer.
+    ///
+    /// Any live cells in the padding past the right edge of the grid are
+    /// cleared. Panics if there isn't exactly one block per 32 cells of each
+    /// row.
+    pub fn from_blocks(width: u32, height: u32, mut blocks: Vec<u32>) -> Self {
+        let block_width = width.div_ceil(32) as usize;
+        assert_eq!(
+            blocks.len(),
+            block_width * height as usize,
+            "Block data has wrong length for a {width}x{height} grid",
+        );
+
+        if !width.is_multiple_of(32) {
+            let padding_mask = (1 << (width % 32)) - 1;
+            for row in blocks.chunks_mut(block_width) {
+                row[block_width - 1] &= padding_mask;
+            }
+        }
+
+        Self {
+            size: [width, height],
+            blocks,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.size[0]
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size[1]
+    }
+
+    /// The width and height of the grid in cells.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// The width and height of the grid in blocks.
+    pub fn physical_size(&self) -> [u32; 2] {
+        [self.size[0].div_ceil(32), self.size[1]]
+    }
+
+    /// The blocks making up the grid, row by row.
+    pub fn blocks(&self) -> &[u32] {
+        &self.blocks
+    }
+
+    pub fn into_blocks(self) -> Vec<u32> {
+        self.blocks
+    }
+
+    /// Returns whether the cell at `x`, `y` is alive.
+    ///
+    /// Panics if the cell is outside of the grid.
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        let (index, bit) = self.locate(x, y);
+        (self.blocks[index] >> bit) & 1 != 0
+    }
+
+    /// Sets whether the cell at `x`, `y` is alive.
+    ///
+    /// Panics if the cell is outside of the grid.
+    pub fn set(&mut self, x: u32, y: u32, alive: bool) {
+        let (index, bit) = self.locate(x, y);
+        if alive {
+            self.blocks[index] |= 1 << bit;
+        } else {
+            self.blocks[index] &= !(1 << bit);
+        }
+    }
+
+    /// Copies all of the cells of `other` into this grid, with the top left
+    /// corner of `other` at `x`, `y`.
+    ///
+    /// Panics if `other` doesn't fit inside the grid at that position.
+    pub fn paste(&mut self, x: u32, y: u32, other: &PackedGrid) {
+        let [width, height] = other.size;
+        assert!(
+            x.checked_add(width).is_some_and(|x1| x1 <= self.size[0])
+                && y.checked_add(height).is_some_and(|y1| y1 <= self.size[1]),
+            "{width}x{height} grid doesn't fit at ({x}, {y}) in {:?} grid",
+            self.size,
+        );
+
+        for dy in 0..height {
+            for dx in 0..width {
+                self.set(x + dx, y + dy, other.get(dx, dy));
+            }
+        }
+    }
+
+    /// Returns an iterator over whether each cell is alive, in row-major
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        let [width, height] = self.size;
+        (0..height).flat_map(move |y| (0..width).map(move |x| self.get(x, y)))
+    }
+
+    /// Returns an iterator over the coordinates of the live cells, in
+    /// row-major order.
+    ///
+    /// Dead blocks are skipped entirely, so this is fast for sparse grids.
+    pub fn live_cells(&self) -> impl Iterator<Item = [u32; 2]> + '_ {
+        let block_width = self.physical_size()[0].max(1) as usize;
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|&(_, &block)| block != 0)
+            .flat_map(move |(index, &block)| {
+                let x0 = (index % block_width) as u32 * 32;
+                let y = (index / block_width) as u32;
+                (0..32)
+                    .filter(move |bit| (block >> bit) & 1 != 0)
+                    .map(move |bit| [x0 + bit, y])
+            })
+    }
+
+    /// Returns the number of live cells.
+    pub fn count(&self) -> u64 {
+        self.blocks
+            .iter()
+            .map(|block| block.count_ones() as u64)
+            .sum()
+    }
+
+    /// Returns the smallest rectangle containing every live cell, or `None` if
+    /// there are no live cells.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let [mut min_x, mut min_y] = [u32::MAX; 2];
+        let [mut max_x, mut max_y] = [0; 2];
+        let block_width = self.physical_size()[0].max(1) as usize;
+        for (index, &block) in self.blocks.iter().enumerate() {
+            if block == 0 {
+                continue;
+            }
+
+            let x0 = (index % block_width) as u32 * 32;
+            let y = (index / block_width) as u32;
+            min_x = min_x.min(x0 + block.trailing_zeros());
+            max_x = max_x.max(x0 + 31 - block.leading_zeros());
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+        }
+
+        (min_x <= max_x).then(|| Bounds {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        })
+    }
+
+    /// Unpacks the grid into one byte per cell in row-major order, with live
+    /// cells set to 1.
+    pub fn to_cells(&self) -> Vec<u8> {
+        unpack_grid(self.size, &self.blocks)
+    }
+
+    /// Returns the index of the block holding a cell, and the bit within that
+    /// block.
+    fn locate(&self, x: u32, y: u32) -> (usize, u32) {
+        let [width, height] = self.size;
+        assert!(
+            x < width && y < height,
+            "Cell ({x}, {y}) is outside of the {width}x{height} grid",
+        );
+
+        let index = y as usize * width.div_ceil(32) as usize + (x / 32) as usize;
+        (index, x % 32)
+    }
+}
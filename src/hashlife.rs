@@ -0,0 +1,613 @@
+use std::collections::HashMap;
+
+use crate::{PackedGrid, ParsePatternError, Pattern, Rule};
+
+mod macrocell;
+
+pub(crate) use macrocell::HEADER as MACROCELL_HEADER;
+
+/// The largest level the root node can grow to. Cell coordinates are `i64`s
+/// centered on the root, and the size of the root has to fit in one too.
+const MAX_LEVEL: u32 = 62;
+
+/// The largest `j` that [`HashLife::step_pow2`] can advance by `2^j`
+/// generations at once, which needs a root three levels larger.
+const MAX_STEP_POW2: u32 = MAX_LEVEL - 3;
+
+/// A handle to a node in [`HashLife::nodes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct NodeId(u32);
+
+/// The level 0 nodes, i.e. single cells.
+const DEAD: NodeId = NodeId(0);
+const ALIVE: NodeId = NodeId(1);
+
+/// A square of `2^level` by `2^level` cells, made of four squares of the next
+/// level down.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level: u32,
+    population: u64,
+
+    /// The top left, top right, bottom left and bottom right quarters of the
+    /// node. Unused for level 0 nodes.
+    children: [NodeId; 4],
+}
+
+/// A CPU implementation of the game of life on an unbounded plane using
+/// Gosper's HashLife algorithm.
+///
+/// The plane is stored as a quadtree where identical squares are shared, and
+/// the result of advancing each square is cached, so patterns with a lot of
+/// repetition in space and time can be advanced by huge numbers of
+/// generations at once with [`HashLife::step_pow2`]. This makes it suitable
+/// for patterns like breeders and metacells that don't fit in a
+/// [`LifeSimulation`], and as a reference to verify the GPU kernels against.
+///
+/// Cells are addressed by `i64` coordinates, with `y` increasing downwards.
+/// Nodes are never freed, so memory use grows with the number of distinct
+/// squares seen over the lifetime of the engine.
+///
+/// [`LifeSimulation`]: crate::LifeSimulation
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+
+    /// Finds the existing node with the given children, so each distinct
+    /// square is only stored once.
+    lookup: HashMap<[NodeId; 4], NodeId>,
+
+    /// The empty node of each level, built on demand.
+    empty: Vec<NodeId>,
+
+    /// The center half of a node advanced by `2^j` generations, keyed by the
+    /// node and `j`.
+    results: HashMap<(NodeId, u32), NodeId>,
+
+    /// The node holding the whole pattern, centered on `(0, 0)`.
+    root: NodeId,
+
+    generation: u64,
+}
+
+impl HashLife {
+    /// Creates an empty plane running `rule`.
+    ///
+    /// Panics if the rule has `B0`, i.e. dead cells with no live neighbors
+    /// are born, since the plane would fill up with live cells.
+    pub fn new(rule: Rule) -> Self {
+        assert!(
+            !rule.is_born(0),
+            "HashLife can't run {rule}, rules with B0 are unsupported",
+        );
+
+        Self::with_any_rule(rule)
+    }
+
+    /// Like [`Self::new`], but accepts rules with `B0`. Planes with those
+    /// rules can hold a pattern while converting between formats, but must
+    /// never be advanced.
+    pub(crate) fn with_any_rule(rule: Rule) -> Self {
+        let mut hashlife = Self {
+            rule,
+            nodes: vec![
+                Node {
+                    level: 0,
+                    population: 0,
+                    children: [DEAD; 4],
+                },
+                Node {
+                    level: 0,
+                    population: 1,
+                    children: [DEAD; 4],
+                },
+            ],
+            lookup: HashMap::new(),
+            empty: vec![DEAD],
+            results: HashMap::new(),
+            root: DEAD,
+            generation: 0,
+        };
+        hashlife.root = hashlife.empty(3);
+        hashlife
+    }
+
+    /// Creates a plane holding `pattern` with its top left corner at `(0, 0)`,
+    /// running the pattern's rule or Conway's game of life if it has none.
+    ///
+    /// Panics if the pattern's rule has `B0`.
+    pub fn from_pattern(pattern: &Pattern) -> Self {
+        let rule = pattern.rule.unwrap_or_default();
+        assert!(
+            !rule.is_born(0),
+            "HashLife can't run {rule}, rules with B0 are unsupported",
+        );
+
+        Self::from_pattern_with_any_rule(pattern)
+    }
+
+    /// Like [`Self::from_pattern`], but accepts rules with `B0`, see
+    /// [`Self::with_any_rule`].
+    pub(crate) fn from_pattern_with_any_rule(pattern: &Pattern) -> Self {
+        let mut hashlife = Self::with_any_rule(pattern.rule.unwrap_or_default());
+        hashlife.write_grid(0, 0, &pattern.grid);
+        hashlife
+    }
+
+    /// Parses a pattern in Golly's macrocell format, keeping the generation
+    /// count and rule stored in the file.
+    ///
+    /// The root node is centered on `(0, 0)`. Returns
+    /// [`ParsePatternError::UnsupportedRule`] if the rule has `B0`.
+    pub fn from_macrocell(s: &str) -> Result<Self, ParsePatternError> {
+        let hashlife = macrocell::parse(s)?;
+        if hashlife.rule.is_born(0) {
+            return Err(ParsePatternError::UnsupportedRule(hashlife.rule));
+        }
+
+        Ok(hashlife)
+    }
+
+    /// Like [`Self::from_macrocell`], but accepts rules with `B0`, see
+    /// [`Self::with_any_rule`].
+    pub(crate) fn from_macrocell_with_any_rule(s: &str) -> Result<Self, ParsePatternError> {
+        macrocell::parse(s)
+    }
+
+    /// Writes the pattern in Golly's macrocell format, including the rule and
+    /// generation count.
+    pub fn to_macrocell(&self) -> String {
+        macrocell::write(self)
+    }
+
+    /// The rule used to advance the pattern.
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// The number of generations the pattern has been advanced.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The number of live cells.
+    pub fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+
+    /// Returns whether the cell at `x`, `y` is alive.
+    pub fn get(&self, x: i64, y: i64) -> bool {
+        let mut node = self.node(self.root);
+        let half = 1i64 << (node.level - 1);
+        if !(-half..half).contains(&x) || !(-half..half).contains(&y) {
+            return false;
+        }
+
+        // Walk down the tree using the offset from the top left corner of
+        // the current node.
+        let [mut x, mut y] = [(x + half) as u64, (y + half) as u64];
+        while node.level > 0 {
+            let half = 1u64 << (node.level - 1);
+            let quarter = (x >= half) as usize + 2 * (y >= half) as usize;
+            node = self.node(node.children[quarter]);
+            x %= half;
+            y %= half;
+        }
+
+        node.population != 0
+    }
+
+    /// Sets whether the cell at `x`, `y` is alive, growing the plane if
+    /// needed.
+    ///
+    /// The plane can't grow past `2^62` cells across, so panics if a live
+    /// cell is set outside of `-2^61..2^61` in either direction.
+    pub fn set(&mut self, x: i64, y: i64, alive: bool) {
+        loop {
+            let half = 1i64 << (self.level(self.root) - 1);
+            if (-half..half).contains(&x) && (-half..half).contains(&y) {
+                let [x, y] = [(x + half) as u64, (y + half) as u64];
+                self.root = self.set_in(self.root, x, y, alive);
+                return;
+            }
+
+            // Dead cells outside of the root are already dead.
+            if !alive {
+                return;
+            }
+
+            self.expand();
+        }
+    }
+
+    /// Sets the cells of `grid` with its top left corner at `x`, `y`. Only the
+    /// live cells are written, so the cells under the dead cells of the grid
+    /// are left untouched.
+    ///
+    /// Panics if a live cell lands outside of the plane, see [`Self::set`].
+    pub fn write_grid(&mut self, x: i64, y: i64, grid: &PackedGrid) {
+        for [cell_x, cell_y] in grid.live_cells() {
+            self.set(x + cell_x as i64, y + cell_y as i64, true);
+        }
+    }
+
+    /// Extracts the `width` by `height` window of cells with its top left
+    /// corner at `x`, `y` as a flat grid, e.g. to seed a [`LifeSimulation`].
+    ///
+    /// [`LifeSimulation`]: crate::LifeSimulation
+    pub fn read_grid(&self, x: i64, y: i64, width: u32, height: u32) -> PackedGrid {
+        let mut grid = PackedGrid::new(width, height);
+        let window = [
+            x,
+            y,
+            x.saturating_add(width as i64),
+            y.saturating_add(height as i64),
+        ];
+        self.visit_live_cells(window, &mut |cell_x, cell_y| {
+            grid.set((cell_x - x) as u32, (cell_y - y) as u32, true);
+        });
+        grid
+    }
+
+    /// Returns the top left corner and the size of the smallest rectangle
+    /// containing every live cell, or `None` if there are no live cells.
+    ///
+    /// Unlike [`Bounds`], the size can be larger than a `u32`, since patterns
+    /// on the plane can spread out arbitrarily far.
+    ///
+    /// [`Bounds`]: crate::Bounds
+    pub fn bounds(&self) -> Option<([i64; 2], [u64; 2])> {
+        let half = 1i64 << (self.level(self.root) - 1);
+        let [left, top, right, bottom] = self.node_bounds(self.root, &mut HashMap::new())?;
+        let origin = [left as i64 - half, top as i64 - half];
+        Some((origin, [right - left + 1, bottom - top + 1]))
+    }
+
+    /// Extracts the live cells as a pattern with the engine's rule, cropped
+    /// to their bounding box. Also returns the position of the top left
+    /// corner of the pattern.
+    ///
+    /// Panics if the live cells span more than `u32::MAX` cells in either
+    /// direction.
+    pub fn to_pattern(&self) -> (Pattern, [i64; 2]) {
+        let Some((origin, size)) = self.bounds() else {
+            return (
+                Pattern::new(PackedGrid::new(0, 0)).with_rule(self.rule),
+                [0, 0],
+            );
+        };
+
+        let [width, height] = size
+            .map(|size| u32::try_from(size).expect("Pattern is too large to extract as a grid"));
+        let grid = self.read_grid(origin[0], origin[1], width, height);
+        (Pattern::new(grid).with_rule(self.rule), origin)
+    }
+
+    /// Advances the pattern by `generations` generations.
+    ///
+    /// Any number of generations can be given. Powers of two past `2^59` are
+    /// advanced in several steps of `2^59`, since that's the most
+    /// [`Self::step_pow2`] can do at once.
+    ///
+    /// Panics if the pattern grows past the edges of the plane, see
+    /// [`Self::set`].
+    pub fn step(&mut self, generations: u64) {
+        for j in 0..u64::BITS {
+            if (generations >> j) & 1 != 0 {
+                let step = j.min(MAX_STEP_POW2);
+                for _ in 0..1u64 << (j - step) {
+                    self.step_pow2(step);
+                }
+            }
+        }
+    }
+
+    /// Advances the pattern by `2^j` generations.
+    ///
+    /// Thanks to the cache this costs about the same for any `j` once the
+    /// pattern has settled into a repeating structure, so large jumps are far
+    /// cheaper than the equivalent number of single steps.
+    ///
+    /// Panics if `j` is larger than `59`, since the pattern could then grow
+    /// past the `i64` coordinates of the plane.
+    pub fn step_pow2(&mut self, j: u32) {
+        assert!(
+            j <= MAX_STEP_POW2,
+            "Can't advance by 2^{j} generations, the pattern could leave the plane",
+        );
+
+        // The root needs to be large enough to advance by 2^j at once, and
+        // the pattern needs a margin of at least 2^j cells so that nothing
+        // escapes the center half of the root that is kept.
+        while self.level(self.root) < j + 2 || !self.is_centered(self.root) {
+            self.expand();
+        }
+        self.expand();
+
+        self.root = self.successor(self.root, j);
+        self.generation = self.generation.wrapping_add(1 << j);
+        self.shrink();
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+
+    fn level(&self, id: NodeId) -> u32 {
+        self.node(id).level
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.node(id).children
+    }
+
+    /// Returns the node with the given quarters, creating it if it doesn't
+    /// exist yet.
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.lookup.get(&children) {
+            return id;
+        }
+
+        let level = self.level(children[0]) + 1;
+        let population = children.iter().fold(0u64, |sum, &child| {
+            sum.saturating_add(self.node(child).population)
+        });
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(Node {
+            level,
+            population,
+            children,
+        });
+        self.lookup.insert(children, id);
+        id
+    }
+
+    /// Returns the node of `level` with all cells dead.
+    fn empty(&mut self, level: u32) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let child = *self.empty.last().unwrap();
+            let node = self.join([child; 4]);
+            self.empty.push(node);
+        }
+
+        self.empty[level as usize]
+    }
+
+    /// Replaces the root with one twice as large with the old root in its
+    /// center.
+    fn expand(&mut self) {
+        let level = self.level(self.root);
+        assert!(
+            level < MAX_LEVEL,
+            "Pattern has grown past the edges of the plane"
+        );
+
+        let empty = self.empty(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let children = [
+            [empty, empty, empty, nw],
+            [empty, empty, ne, empty],
+            [empty, sw, empty, empty],
+            [se, empty, empty, empty],
+        ]
+        .map(|children| self.join(children));
+        self.root = self.join(children);
+    }
+
+    /// Replaces the root with its center half for as long as that doesn't
+    /// lose any live cells, so later steps don't waste time on empty space.
+    fn shrink(&mut self) {
+        while self.level(self.root) > 3 && self.is_centered(self.root) {
+            self.root = self.center(self.root);
+        }
+    }
+
+    /// Returns the center half of a node, which is one level smaller.
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ])
+    }
+
+    /// Returns whether all of the live cells of a node are in its center
+    /// half.
+    fn is_centered(&mut self, id: NodeId) -> bool {
+        let center = self.center(id);
+        self.node(center).population == self.node(id).population
+    }
+
+    /// Returns a copy of a node at `level` with the cell at `x`, `y` relative
+    /// to its top left corner set.
+    fn set_in(&mut self, id: NodeId, x: u64, y: u64, alive: bool) -> NodeId {
+        let level = self.level(id);
+        if level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+
+        let half = 1u64 << (level - 1);
+        let quarter = (x >= half) as usize + 2 * (y >= half) as usize;
+        let mut children = self.children(id);
+        children[quarter] = self.set_in(children[quarter], x % half, y % half, alive);
+        self.join(children)
+    }
+
+    /// Returns the left, top, right and bottom edges of the live cells of a
+    /// node relative to its top left corner, with the right and bottom edges
+    /// included.
+    ///
+    /// Only the children with live cells are visited, and shared nodes are
+    /// only visited once thanks to `cache`, so this is fast even for huge
+    /// repetitive patterns.
+    fn node_bounds(
+        &self,
+        id: NodeId,
+        cache: &mut HashMap<NodeId, Option<[u64; 4]>>,
+    ) -> Option<[u64; 4]> {
+        let node = self.node(id);
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some([0; 4]);
+        }
+        if let Some(&bounds) = cache.get(&id) {
+            return bounds;
+        }
+
+        let half = 1u64 << (node.level - 1);
+        let mut bounds: Option<[u64; 4]> = None;
+        for (quarter, &child) in node.children.iter().enumerate() {
+            let Some([left, top, right, bottom]) = self.node_bounds(child, cache) else {
+                continue;
+            };
+            let [x, y] = [(quarter % 2) as u64 * half, (quarter / 2) as u64 * half];
+            let child = [left + x, top + y, right + x, bottom + y];
+            bounds = Some(match bounds {
+                Some([left, top, right, bottom]) => [
+                    left.min(child[0]),
+                    top.min(child[1]),
+                    right.max(child[2]),
+                    bottom.max(child[3]),
+                ],
+                None => child,
+            });
+        }
+
+        cache.insert(id, bounds);
+        bounds
+    }
+
+    /// Calls `visit` with the coordinates of every live cell inside `window`,
+    /// given as the left, top, right and bottom edges with the right and
+    /// bottom edges excluded.
+    fn visit_live_cells(&self, window: [i64; 4], visit: &mut impl FnMut(i64, i64)) {
+        let half = 1i64 << (self.level(self.root) - 1);
+        self.visit_node(self.root, [-half, -half], window, visit);
+    }
+
+    /// Like [`Self::visit_live_cells`], but only for the cells of a node with
+    /// its top left corner at `origin`.
+    fn visit_node(
+        &self,
+        id: NodeId,
+        origin: [i64; 2],
+        window: [i64; 4],
+        visit: &mut impl FnMut(i64, i64),
+    ) {
+        let node = self.node(id);
+        let size = 1i64 << node.level;
+        let [x, y] = origin;
+        let [left, top, right, bottom] = window;
+        if node.population == 0 || x >= right || y >= bottom || x + size <= left || y + size <= top
+        {
+            return;
+        }
+
+        if node.level == 0 {
+            visit(x, y);
+            return;
+        }
+
+        let half = size / 2;
+        for (quarter, &child) in node.children.iter().enumerate() {
+            let offset = [(quarter % 2) as i64 * half, (quarter / 2) as i64 * half];
+            self.visit_node(child, [x + offset[0], y + offset[1]], window, visit);
+        }
+    }
+
+    /// Returns the center half of a node advanced by `2^j` generations, where
+    /// `j` is at most two less than the level of the node.
+    fn successor(&mut self, id: NodeId, j: u32) -> NodeId {
+        let level = self.level(id);
+        debug_assert!(level >= 2 && j + 2 <= level);
+
+        if self.node(id).population == 0 {
+            return self.empty(level - 1);
+        }
+
+        if let Some(&result) = self.results.get(&(id, j)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.successor_base(id)
+        } else {
+            self.successor_recursive(id, j)
+        };
+        self.results.insert((id, j), result);
+        result
+    }
+
+    /// Advances the center 2x2 cells of a 4x4 node by one generation.
+    fn successor_base(&mut self, id: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (quarter, &child) in self.children(id).iter().enumerate() {
+            for (cell, &grandchild) in self.children(child).iter().enumerate() {
+                let x = (quarter % 2) * 2 + cell % 2;
+                let y = (quarter / 2) * 2 + cell / 2;
+                cells[y][x] = grandchild == ALIVE;
+            }
+        }
+
+        let next = |x: usize, y: usize| {
+            let neighbors = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .filter(|&(nx, ny)| (nx, ny) != (x, y) && cells[ny][nx])
+                .count() as u32;
+            let alive = if cells[y][x] {
+                self.rule.survives(neighbors)
+            } else {
+                self.rule.is_born(neighbors)
+            };
+            if alive { ALIVE } else { DEAD }
+        };
+
+        let children = [next(1, 1), next(2, 1), next(1, 2), next(2, 2)];
+        self.join(children)
+    }
+
+    fn successor_recursive(&mut self, id: NodeId, j: u32) -> NodeId {
+        let level = self.level(id);
+        let [nw, ne, sw, se] = self.children(id);
+        let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+        let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+        let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+        let [se_nw, se_ne, se_sw, _] = self.children(se);
+
+        // The nine overlapping squares of half the size of the node, in
+        // row-major order.
+        let squares = [
+            nw,
+            self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+            ne,
+            self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+            self.join([nw_se, ne_sw, sw_ne, se_nw]),
+            self.join([ne_sw, ne_se, se_nw, se_ne]),
+            sw,
+            self.join([sw_ne, se_nw, sw_se, se_sw]),
+            se,
+        ];
+
+        // Advancing by the maximum of 2^(level - 2) takes two rounds of
+        // 2^(level - 3) generations. Smaller steps skip the first round and
+        // only take the center of each square.
+        let first = if j + 2 == level {
+            squares.map(|square| self.successor(square, j - 1))
+        } else {
+            squares.map(|square| self.center(square))
+        };
+        let second = if j + 2 == level { j - 1 } else { j };
+
+        let [a, b, c, d, e, f, g, h, i] = first;
+        let quarters = [[a, b, d, e], [b, c, e, f], [d, e, g, h], [e, f, h, i]];
+        let result = quarters.map(|quarter| {
+            let node = self.join(quarter);
+            self.successor(node, second)
+        });
+        self.join(result)
+    }
+}
@@ -0,0 +1,236 @@
+//! Golly's macrocell format, which stores the quadtree used by HashLife
+//! directly so that huge but repetitive patterns stay small. See
+//! <https://golly.sourceforge.io/Help/formats.html#mc>.
+
+use std::{collections::HashMap, fmt::Write};
+
+use super::{ALIVE, DEAD, HashLife, NodeId};
+use crate::{
+    ParsePatternError, Rule,
+    pattern::{parse_rule, split_directive},
+};
+
+/// The first line of a macrocell file, which may be followed by the name of
+/// the program that wrote it.
+pub(crate) const HEADER: &str = "[M2]";
+
+/// The level of the nodes written as 8x8 blocks of cells.
+const LEAF_LEVEL: u32 = 3;
+
+pub(super) fn parse(s: &str) -> Result<HashLife, ParsePatternError> {
+    let mut lines = s.lines().enumerate();
+    match lines.next() {
+        Some((_, line)) if line.starts_with(HEADER) => {}
+        line => {
+            let line = line.map_or("", |(_, line)| line);
+            return Err(ParsePatternError::InvalidHeader(line.to_owned()));
+        }
+    }
+
+    let mut rule = Rule::CONWAY;
+    let mut generation = 0;
+    let mut node_lines = Vec::new();
+    for (index, line) in lines {
+        let line = line.trim();
+        if let Some(directive) = line.strip_prefix('#') {
+            let (kind, text) = split_directive(directive);
+            let text = text.trim();
+            match kind {
+                "R" => rule = parse_rule(text)?,
+                "G" => {
+                    generation = text
+                        .parse()
+                        .map_err(|_| ParsePatternError::InvalidHeader(line.to_owned()))?;
+                }
+                _ => {}
+            }
+        } else if !line.is_empty() {
+            node_lines.push((index, line));
+        }
+    }
+
+    // The rule is checked by the callers that need to run the pattern.
+    let mut hashlife = HashLife::with_any_rule(rule);
+    hashlife.generation = generation;
+
+    // Each line defines a node, and nodes refer to their children by line
+    // number starting from 1.
+    let mut nodes = Vec::<NodeId>::new();
+    for (index, line) in node_lines {
+        let invalid = || ParsePatternError::InvalidNode { line: index + 1 };
+        let node = if line.starts_with(|c: char| c.is_ascii_digit()) {
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+            let &[level, ref children @ ..] = &values[..] else {
+                return Err(invalid());
+            };
+            let children: [usize; 4] = children.try_into().map_err(|_| invalid())?;
+            if !(1..=super::MAX_LEVEL as usize).contains(&level) {
+                return Err(invalid());
+            }
+
+            let level = level as u32;
+            let mut ids = [DEAD; 4];
+            for (id, child) in ids.iter_mut().zip(children) {
+                *id = if level == 1 {
+                    // Children of level 1 nodes are cell states.
+                    if child == 0 { DEAD } else { ALIVE }
+                } else if child == 0 {
+                    hashlife.empty(level - 1)
+                } else {
+                    match nodes.get(child - 1) {
+                        Some(&id) if hashlife.level(id) == level - 1 => id,
+                        _ => return Err(invalid()),
+                    }
+                };
+            }
+            hashlife.join(ids)
+        } else {
+            let cells = parse_leaf(line).ok_or_else(invalid)?;
+            build(&mut hashlife, LEAF_LEVEL, [0, 0], &cells)
+        };
+        nodes.push(node);
+    }
+
+    if let Some(&root) = nodes.last() {
+        hashlife.root = root;
+        while hashlife.level(hashlife.root) < LEAF_LEVEL {
+            hashlife.expand();
+        }
+    }
+
+    Ok(hashlife)
+}
+
+/// Parses an 8x8 block of cells, written as rows of `.` and `*` each ended by
+/// a `$`. Dead cells at the end of a row, and empty rows at the end of the
+/// block, can be left out.
+fn parse_leaf(line: &str) -> Option<[[bool; 8]; 8]> {
+    let mut cells = [[false; 8]; 8];
+    let [mut x, mut y] = [0, 0];
+    for character in line.chars() {
+        match character {
+            '.' => x += 1,
+            '*' => {
+                *cells.get_mut(y)?.get_mut(x)? = true;
+                x += 1;
+            }
+            '$' => {
+                x = 0;
+                y += 1;
+            }
+            _ => return None,
+        }
+
+        if x > 8 {
+            return None;
+        }
+    }
+
+    Some(cells)
+}
+
+/// Builds the node of `level` with its top left corner at `origin` in
+/// `cells`.
+fn build(
+    hashlife: &mut HashLife,
+    level: u32,
+    origin: [usize; 2],
+    cells: &[[bool; 8]; 8],
+) -> NodeId {
+    let [x, y] = origin;
+    if level == 0 {
+        return if cells[y][x] { ALIVE } else { DEAD };
+    }
+
+    let half = 1 << (level - 1);
+    let children = [[x, y], [x + half, y], [x, y + half], [x + half, y + half]]
+        .map(|origin| build(hashlife, level - 1, origin, cells));
+    hashlife.join(children)
+}
+
+pub(super) fn write(hashlife: &HashLife) -> String {
+    let mut output = format!("{HEADER} (wgpu-gol {})\n", env!("CARGO_PKG_VERSION"));
+    writeln!(output, "#R {}", hashlife.rule).unwrap();
+    if hashlife.generation != 0 {
+        writeln!(output, "#G {}", hashlife.generation).unwrap();
+    }
+
+    if hashlife.population() != 0 {
+        let mut writer = NodeWriter {
+            hashlife,
+            output: &mut output,
+            lines: HashMap::new(),
+        };
+        writer.write_node(hashlife.root);
+    }
+
+    output
+}
+
+/// Writes each distinct node once, after all of its children.
+struct NodeWriter<'a> {
+    hashlife: &'a HashLife,
+    output: &'a mut String,
+
+    /// The line number each node has been written on.
+    lines: HashMap<NodeId, usize>,
+}
+
+impl NodeWriter<'_> {
+    /// Writes a node if it hasn't been written yet, returning its line
+    /// number, or 0 if the node is empty.
+    fn write_node(&mut self, id: NodeId) -> usize {
+        let node = *self.hashlife.node(id);
+        if node.population == 0 {
+            return 0;
+        }
+        if let Some(&line) = self.lines.get(&id) {
+            return line;
+        }
+
+        if node.level == LEAF_LEVEL {
+            let mut rows = (0..8)
+                .map(|y| {
+                    let row = (0..8)
+                        .map(|x| if self.cell(id, x, y) { '*' } else { '.' })
+                        .collect::<String>();
+                    row.trim_end_matches('.').to_owned()
+                })
+                .collect::<Vec<_>>();
+            while rows.last().is_some_and(String::is_empty) {
+                rows.pop();
+            }
+
+            for row in rows {
+                self.output.push_str(&row);
+                self.output.push('$');
+            }
+            self.output.push('\n');
+        } else {
+            let children = node.children.map(|child| self.write_node(child));
+            let [nw, ne, sw, se] = children;
+            writeln!(self.output, "{} {nw} {ne} {sw} {se}", node.level).unwrap();
+        }
+
+        let line = self.lines.len() + 1;
+        self.lines.insert(id, line);
+        line
+    }
+
+    /// Returns whether the cell at `x`, `y` of a leaf node is alive.
+    fn cell(&self, mut id: NodeId, mut x: u32, mut y: u32) -> bool {
+        for level in (1..=LEAF_LEVEL).rev() {
+            let half = 1 << (level - 1);
+            let quarter = (x >= half) as usize + 2 * (y >= half) as usize;
+            id = self.hashlife.node(id).children[quarter];
+            x %= half;
+            y %= half;
+        }
+
+        id == ALIVE
+    }
+}
@@ -1,31 +1,125 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
-};
+use std::sync::Mutex;
+
 use wgpu::util::DeviceExt;
 
+use crate::{readback::StagingRing, stats::StatsPipeline};
+
+pub use crate::{
+    animation::{Animation, AnimationFormat},
+    config::{Kernel, SimulationConfig},
+    error::SimError,
+    grid::PackedGrid,
+    hashlife::HashLife,
+    pattern::{ParsePatternError, Pattern, PatternFormat},
+    readback::{EncodedReadback, StateReadback},
+    reference::ReferenceSimulation,
+    render::{Camera, Image, Palette, Renderer},
+    rule::{ParseRuleError, Rule},
+    stats::{Bounds, GridStats, StatsOptions},
+    topology::{Edges, ParseTopologyError, Topology},
+};
+
+mod animation;
+mod config;
+mod error;
+mod grid;
+mod hashlife;
+mod pattern;
+mod readback;
+mod reference;
+mod render;
+mod rule;
+mod stats;
+mod topology;
+
 const WORKGROUP_SIZE: u32 = 64;
 
+/// The source of the simulation shader, embedded at compile time so that the
+/// library works regardless of the working directory.
+const SHADER_SOURCE: &str = include_str!("shaders.wgsl");
+
+/// Where the shader is read from when hot reloading.
+#[cfg(feature = "hot-reload")]
+const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders.wgsl");
+
+/// The size in blocks of the tiles processed by each workgroup when using
+/// [`Kernel::Tiled`]. Must match `TILE_WIDTH` and `TILE_HEIGHT` in the shader.
+const TILE_SIZE: [u32; 2] = [8, 8];
+
+/// The size in blocks of the tiles processed by each workgroup when using
+/// [`Kernel::Temporal`]. Must match `TILE_WIDTH` and `TEMPORAL_HEIGHT` in the
+/// shader.
+const TEMPORAL_TILE_SIZE: [u32; 2] = [8, 32];
+
 pub struct LifeSimulation {
-    pub instance: wgpu::Instance,
-    pub adapter: wgpu::Adapter,
+    /// The instance the adapter was picked from, or `None` if the simulation
+    /// was created on an existing device with [`Self::from_device`].
+    pub instance: Option<wgpu::Instance>,
+
+    /// The adapter the device was requested from, or `None` if the simulation
+    /// was created on an existing device with [`Self::from_device`].
+    pub adapter: Option<wgpu::Adapter>,
+
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+
+    /// The layout of [`Self::bind_groups`], for building pipelines that read
+    /// the simulation state, e.g. to render it. Every binding but `out_state`
+    /// is visible to both compute and fragment shaders.
+    pub bind_group_layout: wgpu::BindGroupLayout,
+
     pub pipeline_layout: wgpu::PipelineLayout,
     pub compute_pipeline: wgpu::ComputePipeline,
+
+    /// Pipeline for advancing a single generation when using
+    /// [`Kernel::Temporal`], used when the number of generations to run isn't
+    /// a multiple of the generations per pass.
+    pub single_step_pipeline: Option<wgpu::ComputePipeline>,
+
     pub bind_groups: [wgpu::BindGroup; 2],
     pub state_bufs: [wgpu::Buffer; 2],
+
+    /// The buffer [`Self::encode_read`] copies the state into, for callers
+    /// that map it themselves.
     pub read_buf: wgpu::Buffer,
 
+    /// Staging buffers used to read the state back to the CPU, behind a lock
+    /// so that reads don't need mutable access to the simulation.
+    staging: Mutex<StagingRing>,
+
+    /// Reductions computing statistics about the state on the GPU.
+    stats: StatsPipeline,
+
+    pub rule_buf: wgpu::Buffer,
+    pub topology_buf: wgpu::Buffer,
+
+    /// The number of generations the simulation has advanced.
     pub step: u64,
 
-    /// The size in **cells** of the grid. This will be different from the
-    /// number of blocks in the grid.
-    pub logical_grid_size: u32,
+    /// The index of the buffer in `state_bufs` holding the current generation.
+    /// This is also the index of the bind group in `bind_groups` that reads
+    /// from that buffer.
+    pub current_state: usize,
+
+    /// The rule currently used to advance the simulation.
+    ///
+    /// Use [`LifeSimulation::set_rule`] to change the rule, modifying this
+    /// directly won't update the rule used by the GPU.
+    pub rule: Rule,
+
+    /// What happens at the edges of the grid.
+    pub topology: Topology,
+
+    /// The compute kernel used to advance the simulation.
+    pub kernel: Kernel,
+
+    /// The width and height in **cells** of the grid. This will be different
+    /// from the number of blocks in the grid.
+    pub logical_grid_size: [u32; 2],
 
     /// The total number of cells in the grid.
     ///
-    /// Always `logical_grid_size` squared.
+    /// Always `logical_grid_size[0] * logical_grid_size[1]`.
     pub num_cells: usize,
 
     /// The size in **blocks** of the grid. This will not be square because the
@@ -36,49 +130,122 @@ pub struct LifeSimulation {
     ///
     /// Always `physical_grid_size[0] * physical_grid_size[1]`.
     pub num_blocks: u32,
+
+    /// When the shader file was last modified as of the last (re)load, used
+    /// to detect changes in [`LifeSimulation::reload_shader`].
+    #[cfg(feature = "hot-reload")]
+    shader_modified: Option<std::time::SystemTime>,
 }
 
 impl LifeSimulation {
-    pub async fn new(grid_size: u32, initial_state: &[u8]) -> Self {
-        let num_cells = (grid_size * grid_size) as usize;
+    /// Creates a new `width` by `height` simulation running Conway's game of
+    /// life.
+    ///
+    /// `initial_state` has one byte per cell in row-major order, with any
+    /// non-zero value marking a live cell.
+    pub async fn new(width: u32, height: u32, initial_state: &[u8]) -> Self {
+        Self::with_config(width, height, initial_state, SimulationConfig::default()).await
+    }
 
-        // Make sure the initial state is the right size.
-        assert!(
-            initial_state.len() == num_cells,
-            "Initial state has wrong size, expected {} but got {}",
-            num_cells,
-            initial_state.len(),
-        );
+    /// Creates a new `width` by `height` simulation using the rule and
+    /// topology specified in `config`.
+    ///
+    /// Panics if the simulation can't be created, see [`Self::try_new`] for a
+    /// version that returns an error instead.
+    pub async fn with_config(
+        width: u32,
+        height: u32,
+        initial_state: &[u8],
+        config: SimulationConfig,
+    ) -> Self {
+        Self::try_new(width, height, initial_state, config)
+            .await
+            .unwrap_or_else(|err| panic!("Failed to create simulation: {err}"))
+    }
 
-        // Convert the list of bytes to a list of u32s.
-        let (packed_state, physical_grid_size) = pack_grid(grid_size, initial_state);
-        let num_blocks = physical_grid_size[0] * physical_grid_size[1];
+    /// Creates a new `width` by `height` simulation using the rule and
+    /// topology specified in `config`, returning an error if the grid is
+    /// invalid, no GPU is available, or the grid doesn't fit within the
+    /// device's limits.
+    pub async fn try_new(
+        width: u32,
+        height: u32,
+        initial_state: &[u8],
+        config: SimulationConfig,
+    ) -> Result<Self, SimError> {
+        validate_grid(width, height, initial_state, config.kernel)?;
+        let grid = PackedGrid::from_cells(width, height, initial_state);
+        Self::try_from_grid(&grid, config).await
+    }
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                ..Default::default()
-            })
-            .await
-            .unwrap();
+    /// Creates a new simulation starting from `grid`, using the rule and
+    /// topology specified in `config`.
+    ///
+    /// Returns the same errors as [`Self::try_new`].
+    pub async fn try_from_grid(
+        grid: &PackedGrid,
+        config: SimulationConfig,
+    ) -> Result<Self, SimError> {
+        // Check the grid before going through the trouble of getting a device.
+        validate_dimensions(grid.size(), config.kernel)?;
 
-        dbg!(adapter.get_info());
+        let (instance, adapter) = config.request_adapter().await?;
+        let (device, queue) = config.request_device_from(&adapter).await?;
+        let mut sim = Self::from_device_with_grid(&device, &queue, grid, config).await?;
+        sim.instance = Some(instance);
+        sim.adapter = Some(adapter);
+        Ok(sim)
+    }
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default())
-            .await
-            .unwrap();
+    /// Creates a new `width` by `height` simulation on an existing device,
+    /// e.g. one that an application also uses for rendering.
+    ///
+    /// The simulation keeps its own handles to `device` and `queue`, and all
+    /// of its resources are created on them, so the state buffers and
+    /// [`Self::bind_group_layout`] can be used directly in the application's
+    /// own passes. The adapter and device options in `config` are ignored.
+    pub async fn from_device(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        initial_state: &[u8],
+        config: SimulationConfig,
+    ) -> Result<Self, SimError> {
+        validate_grid(width, height, initial_state, config.kernel)?;
+        let grid = PackedGrid::from_cells(width, height, initial_state);
+        Self::from_device_with_grid(device, queue, &grid, config).await
+    }
 
-        let grid_sizef_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Grid Size Float Buffer"),
-            contents: bytemuck::cast_slice(&[grid_size as f32, grid_size as f32]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+    /// Creates a new simulation starting from `grid` on an existing device, see
+    /// [`Self::from_device`].
+    pub async fn from_device_with_grid(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        grid: &PackedGrid,
+        config: SimulationConfig,
+    ) -> Result<Self, SimError> {
+        let SimulationConfig {
+            rule,
+            topology,
+            kernel,
+            ..
+        } = config;
+
+        let num_cells = validate_dimensions(grid.size(), kernel)?;
+
+        let grid_size = grid.size();
+        let physical_grid_size = grid.physical_size();
+        let num_blocks = physical_grid_size[0] * physical_grid_size[1];
+
+        check_limits(&device.limits(), physical_grid_size, kernel)?;
+
+        let device = device.clone();
+        let queue = queue.clone();
 
         let grid_sizeu_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Grid Size U32 Buffer"),
-            contents: bytemuck::cast_slice(&[grid_size, grid_size]),
+            contents: bytemuck::cast_slice(&grid_size),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -89,24 +256,29 @@ impl LifeSimulation {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
 
+        let rule_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Rule Buffer"),
+            contents: bytemuck::cast_slice(&rule.to_masks()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let topology_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Topology Buffer"),
+            contents: bytemuck::cast_slice(&topology.to_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Everything but the next generation can also be read by fragment
+        // shaders, so that applications can draw the state in their own
+        // render passes.
+        let render_visibility = wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE;
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Grid Bind Group Layout"),
             entries: &[
-                // grid_sizef
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
                 // grid_sizeu
                 wgpu::BindGroupLayoutEntry {
                     binding: 4,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    visibility: render_visibility,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -117,7 +289,7 @@ impl LifeSimulation {
                 // physical_grid_size
                 wgpu::BindGroupLayoutEntry {
                     binding: 3,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    visibility: render_visibility,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -128,7 +300,7 @@ impl LifeSimulation {
                 // in_state
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    visibility: render_visibility,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
@@ -147,12 +319,34 @@ impl LifeSimulation {
                     },
                     count: None,
                 },
+                // rule
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: render_visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // topology
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: render_visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
         let cell_state_buffer_a = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Cell State Buffer A"),
-            contents: bytemuck::cast_slice(&packed_state),
+            contents: bytemuck::cast_slice(grid.blocks()),
             usage: wgpu::BufferUsages::STORAGE
                 | wgpu::BufferUsages::COPY_DST
                 | wgpu::BufferUsages::COPY_SRC,
@@ -171,10 +365,6 @@ impl LifeSimulation {
             label: Some("Bind Group A"),
             layout: &bind_group_layout,
             entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: grid_sizef_buffer.as_entire_binding(),
-                },
                 wgpu::BindGroupEntry {
                     binding: 4,
                     resource: grid_sizeu_buffer.as_entire_binding(),
@@ -191,6 +381,14 @@ impl LifeSimulation {
                     binding: 2,
                     resource: cell_state_buffer_b.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: rule_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: topology_buf.as_entire_binding(),
+                },
             ],
         });
 
@@ -198,10 +396,6 @@ impl LifeSimulation {
             label: Some("Bind Group B"),
             layout: &bind_group_layout,
             entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: grid_sizef_buffer.as_entire_binding(),
-                },
                 wgpu::BindGroupEntry {
                     binding: 4,
                     resource: grid_sizeu_buffer.as_entire_binding(),
@@ -218,52 +412,99 @@ impl LifeSimulation {
                     binding: 2,
                     resource: cell_state_buffer_a.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: rule_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: topology_buf.as_entire_binding(),
+                },
             ],
         });
 
-        let read_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Read Buffer"),
-            contents: bytemuck::cast_slice(&empty_state),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-        });
-
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
+            label: Some("Compute Pipeline Layout"),
             bind_group_layouts: &[&bind_group_layout],
             push_constant_ranges: &[],
         });
 
-        let shader =
-            std::fs::read_to_string("src/shaders.wgsl").expect("Failed to read shader file");
-        let simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Simulation Shader"),
-            source: wgpu::ShaderSource::Wgsl(shader.into()),
-        });
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Simulation Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &simulation_shader,
-            entry_point: Some("compute_main"),
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-            cache: None,
+        let (compute_pipeline, single_step_pipeline) =
+            create_pipelines(&device, &pipeline_layout, SHADER_SOURCE, kernel).await?;
+
+        let state_bufs = [cell_state_buffer_a, cell_state_buffer_b];
+
+        let read_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Read Buffer"),
+            contents: bytemuck::cast_slice(&empty_state),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
         });
+        let stats = StatsPipeline::new(
+            &device,
+            &state_bufs,
+            &physical_grid_size_buffer,
+            grid_size,
+            physical_grid_size,
+        );
 
-        Self {
-            instance,
-            adapter,
+        Ok(Self {
+            instance: None,
+            adapter: None,
             device,
             queue,
+            bind_group_layout,
             pipeline_layout,
             compute_pipeline,
+            single_step_pipeline,
             bind_groups: [bind_group_a, bind_group_b],
-            state_bufs: [cell_state_buffer_a, cell_state_buffer_b],
+            state_bufs,
             read_buf,
+            staging: Mutex::default(),
+            stats,
+            rule_buf,
+            topology_buf,
             step: 0,
+            current_state: 0,
+            rule,
+            topology,
+            kernel,
             logical_grid_size: grid_size,
             num_cells,
             physical_grid_size,
             num_blocks,
+            #[cfg(feature = "hot-reload")]
+            shader_modified: shader_modified_time(),
+        })
+    }
+
+    /// Recompiles the compute pipelines if `src/shaders.wgsl` has changed on
+    /// disk since it was last loaded, returning whether the pipelines were
+    /// rebuilt.
+    ///
+    /// If the new shader fails to compile the previous pipelines are kept, so
+    /// the simulation keeps running while the shader is being fixed.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shader(&mut self) -> Result<bool, SimError> {
+        let modified = shader_modified_time();
+        if modified == self.shader_modified {
+            return Ok(false);
         }
+
+        // Only try each version of the file once, otherwise a broken shader
+        // would be recompiled (and the error reported) on every call.
+        self.shader_modified = modified;
+
+        let source = std::fs::read_to_string(SHADER_PATH).map_err(SimError::ReadShader)?;
+        let (compute_pipeline, single_step_pipeline) = pollster::block_on(create_pipelines(
+            &self.device,
+            &self.pipeline_layout,
+            &source,
+            self.kernel,
+        ))?;
+
+        self.compute_pipeline = compute_pipeline;
+        self.single_step_pipeline = single_step_pipeline;
+        Ok(true)
     }
 
     // Restarts the simulation
@@ -276,17 +517,55 @@ impl LifeSimulation {
             state.len(),
         );
 
-        // Reset the step counter so that we're always writing to the first
-        // buffer and that buffer will be the input for the next tick.
+        let [width, height] = self.logical_grid_size;
+        self.reset_grid(&PackedGrid::from_cells(width, height, state));
+    }
+
+    /// Restarts the simulation from `grid`, which must be the same size as the
+    /// simulation.
+    pub fn reset_grid(&mut self, grid: &PackedGrid) {
+        assert_eq!(
+            grid.size(),
+            self.logical_grid_size,
+            "Grid has wrong size, expected {:?} but got {:?}",
+            self.logical_grid_size,
+            grid.size(),
+        );
+
+        // Reset the step counter and always write to the first buffer so that
+        // buffer will be the input for the next tick.
         self.step = 0;
+        self.current_state = 0;
 
-        // Convert the list of bytes to a list of u32s.
-        let in_state = pack_grid(self.logical_grid_size, state).0;
+        self.queue
+            .write_buffer(&self.state_bufs[0], 0, bytemuck::cast_slice(grid.blocks()));
+    }
 
+    /// Changes the rule used to advance the simulation.
+    ///
+    /// The new rule takes effect for the next compute pass that is submitted,
+    /// the current state of the grid is left untouched.
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
         self.queue
-            .write_buffer(&self.state_bufs[0], 0, bytemuck::cast_slice(&in_state));
+            .write_buffer(&self.rule_buf, 0, bytemuck::cast_slice(&rule.to_masks()));
     }
 
+    /// Returns the bind group that reads from the buffer holding the current
+    /// generation.
+    pub fn current_bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_groups[self.current_state]
+    }
+
+    /// Returns the buffer holding the current generation, packed as described
+    /// in [`pack_grid`].
+    pub fn current_state_buffer(&self) -> &wgpu::Buffer {
+        &self.state_bufs[self.current_state]
+    }
+
+    /// Encodes a compute pass advancing the simulation by the kernel's
+    /// generations per pass, which is a single generation unless using
+    /// [`Kernel::Temporal`].
     pub fn encode_compute_pass(&mut self, encoder: &mut wgpu::CommandEncoder) {
         let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Compute Pass"),
@@ -294,12 +573,361 @@ impl LifeSimulation {
         });
 
         compute_pass.set_pipeline(&self.compute_pipeline);
-        compute_pass.set_bind_group(0, &self.bind_groups[(self.step % 2) as usize], &[]);
-        compute_pass.dispatch_workgroups(self.num_blocks.div_ceil(WORKGROUP_SIZE), 1, 1);
+        compute_pass.set_bind_group(0, self.current_bind_group(), &[]);
+
+        let [width, height] = self.physical_grid_size;
+        match self.kernel {
+            Kernel::Direct => {
+                let max = self.device.limits().max_compute_workgroups_per_dimension;
+                let [x, y] = direct_dispatch_size(self.num_blocks, max);
+                compute_pass.dispatch_workgroups(x, y, 1);
+            }
+
+            Kernel::Tiled => {
+                compute_pass.dispatch_workgroups(
+                    width.div_ceil(TILE_SIZE[0]),
+                    height.div_ceil(TILE_SIZE[1]),
+                    1,
+                );
+            }
+
+            Kernel::Temporal { .. } => {
+                compute_pass.dispatch_workgroups(
+                    width.div_ceil(TEMPORAL_TILE_SIZE[0]),
+                    height.div_ceil(TEMPORAL_TILE_SIZE[1]),
+                    1,
+                );
+            }
+        }
 
         drop(compute_pass);
 
-        self.step += 1;
+        self.step += self.kernel.generations_per_pass() as u64;
+        self.current_state = 1 - self.current_state;
+    }
+
+    /// Encodes the compute passes needed to advance the simulation by exactly
+    /// `generations` generations.
+    ///
+    /// With [`Kernel::Temporal`] this runs as many full passes as possible,
+    /// then advances the remaining generations one at a time.
+    pub fn encode_steps(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u64) {
+        let per_pass = self.kernel.generations_per_pass() as u64;
+        for _ in 0..generations / per_pass {
+            self.encode_compute_pass(encoder);
+        }
+
+        let remaining = generations % per_pass;
+        if remaining == 0 {
+            return;
+        }
+
+        let pipeline = self
+            .single_step_pipeline
+            .as_ref()
+            .expect("Kernels without a single step pipeline always run one generation per pass");
+        let [width, height] = self.physical_grid_size;
+        for _ in 0..remaining {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Single Step Compute Pass"),
+                timestamp_writes: None,
+            });
+
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &self.bind_groups[self.current_state], &[]);
+            compute_pass.dispatch_workgroups(
+                width.div_ceil(TILE_SIZE[0]),
+                height.div_ceil(TILE_SIZE[1]),
+                1,
+            );
+
+            drop(compute_pass);
+
+            self.step += 1;
+            self.current_state = 1 - self.current_state;
+        }
+    }
+
+    /// Starts reading the current grid state from the GPU without blocking,
+    /// returning a future that resolves to one byte per cell.
+    ///
+    /// The state is captured as of the call, so the simulation can keep
+    /// running while the read is in flight, and several reads can be in flight
+    /// at once. The future only makes progress while the device is polled or
+    /// work is submitted to the queue, e.g. by an application's render loop.
+    pub fn read_state_async(&self) -> StateReadback {
+        let grid_size = self.logical_grid_size;
+        self.staging.lock().unwrap().read(
+            &self.device,
+            &self.queue,
+            &self.state_bufs[self.current_state],
+            0,
+            self.num_blocks,
+            move |blocks| unpack_grid(grid_size, blocks),
+        )
+    }
+
+    /// Starts reading the current grid state from the GPU without blocking,
+    /// like [`Self::read_state_async`] but without unpacking the blocks.
+    pub fn read_grid_async(&self) -> StateReadback<PackedGrid> {
+        let [width, height] = self.logical_grid_size;
+        self.staging.lock().unwrap().read(
+            &self.device,
+            &self.queue,
+            &self.state_bufs[self.current_state],
+            0,
+            self.num_blocks,
+            move |blocks| PackedGrid::from_blocks(width, height, blocks.to_vec()),
+        )
+    }
+
+    /// Reads the current grid state from the GPU, blocking until the read
+    /// completes.
+    pub fn read_grid(&self) -> PackedGrid {
+        let readback = self.read_grid_async();
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .expect("Failed to poll device");
+
+        pollster::block_on(readback).expect("Failed to map staging buffer")
+    }
+
+    /// Starts reading the `width` by `height` rectangle of cells with its top
+    /// left corner at `x`, `y`, returning a future that resolves to one byte
+    /// per cell in the rectangle, in row-major order.
+    ///
+    /// Only the rows of blocks overlapping the rectangle are copied from the
+    /// GPU. See [`Self::read_state_async`] for how the read progresses.
+    ///
+    /// Panics if the rectangle doesn't fit in the grid.
+    pub fn read_region_async(&self, x: u32, y: u32, width: u32, height: u32) -> StateReadback {
+        let span = self.region_span(x, y, width, height);
+        let physical_width = self.physical_grid_size[0];
+        self.staging.lock().unwrap().read(
+            &self.device,
+            &self.queue,
+            &self.state_bufs[self.current_state],
+            span.start,
+            span.len() as u32,
+            move |blocks| {
+                let mut cells = Vec::with_capacity(width as usize * height as usize);
+                for row in y..y + height {
+                    for col in x..x + width {
+                        let block = blocks[(row * physical_width + col / 32 - span.start) as usize];
+                        cells.push(((block >> (col % 32)) & 1) as u8);
+                    }
+                }
+                cells
+            },
+        )
+    }
+
+    /// Reads the `width` by `height` rectangle of cells with its top left
+    /// corner at `x`, `y`, blocking until the read completes.
+    ///
+    /// Returns one byte per cell in the rectangle, in row-major order. Panics
+    /// if the rectangle doesn't fit in the grid.
+    pub fn read_region(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+        let readback = self.read_region_async(x, y, width, height);
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .expect("Failed to poll device");
+
+        pollster::block_on(readback).expect("Failed to map staging buffer")
+    }
+
+    /// Overwrites the `width` by `height` rectangle of cells with its top left
+    /// corner at `x`, `y`, leaving the rest of the grid untouched.
+    ///
+    /// `cells` has one byte per cell in the rectangle in row-major order, with
+    /// any non-zero value marking a live cell. Only the blocks overlapping the
+    /// rectangle are written. If the rectangle's left or right edge falls in
+    /// the middle of a block, the overlapping rows are read back first so the
+    /// cells outside the rectangle can be preserved, which blocks until the
+    /// GPU catches up.
+    ///
+    /// Panics if the rectangle doesn't fit in the grid, or if `cells` has the
+    /// wrong length.
+    pub fn write_region(&mut self, x: u32, y: u32, width: u32, height: u32, cells: &[u8]) {
+        assert_eq!(
+            cells.len(),
+            width as usize * height as usize,
+            "Region data has wrong length, expected {} but got {}",
+            width as usize * height as usize,
+            cells.len(),
+        );
+
+        let span = self.region_span(x, y, width, height);
+        let physical_width = self.physical_grid_size[0];
+        let first_col = x / 32;
+        let last_col = (x + width - 1) / 32;
+
+        // Blocks that are only partially covered by the rectangle need the
+        // cells on the other side of the edge. The bits past the right edge of
+        // the grid are always dead, so a rectangle reaching the right edge
+        // doesn't need them.
+        let aligned = x.is_multiple_of(32)
+            && ((x + width).is_multiple_of(32) || x + width == self.logical_grid_size[0]);
+        let mut blocks = if aligned {
+            vec![0; span.len()]
+        } else {
+            let readback = self.staging.lock().unwrap().read(
+                &self.device,
+                &self.queue,
+                &self.state_bufs[self.current_state],
+                span.start,
+                span.len() as u32,
+                |blocks| blocks.to_vec(),
+            );
+            self.device
+                .poll(wgpu::PollType::Wait)
+                .expect("Failed to poll device");
+            pollster::block_on(readback).expect("Failed to map staging buffer")
+        };
+
+        for (row, row_cells) in (y..y + height).zip(cells.chunks(width as usize)) {
+            for (col, &cell) in (x..x + width).zip(row_cells) {
+                let block = &mut blocks[(row * physical_width + col / 32 - span.start) as usize];
+                let mask = 1 << (col % 32);
+                if cell != 0 {
+                    *block |= mask;
+                } else {
+                    *block &= !mask;
+                }
+            }
+
+            // Write each row separately, since the blocks between the rows
+            // belong to cells outside the rectangle.
+            let start = (row * physical_width + first_col - span.start) as usize;
+            let end = (row * physical_width + last_col - span.start) as usize;
+            self.queue.write_buffer(
+                &self.state_bufs[self.current_state],
+                ((span.start as usize + start) * size_of::<u32>()) as u64,
+                bytemuck::cast_slice(&blocks[start..=end]),
+            );
+        }
+    }
+
+    /// Places `pattern` into the grid with its top left corner at `x`, `y`,
+    /// overwriting the cells it covers, see [`Self::write_region`].
+    ///
+    /// If the pattern specifies a rule, the simulation switches to it.
+    ///
+    /// Panics if the pattern doesn't fit in the grid at that position.
+    pub fn write_pattern(&mut self, x: u32, y: u32, pattern: &Pattern) {
+        if let Some(rule) = pattern.rule {
+            self.set_rule(rule);
+        }
+
+        if pattern.width() > 0 && pattern.height() > 0 {
+            let cells = pattern.grid.to_cells();
+            self.write_region(x, y, pattern.width(), pattern.height(), &cells);
+        }
+    }
+
+    /// Reads the current grid state as a pattern with the simulation's rule,
+    /// blocking until the read completes.
+    pub fn read_pattern(&self) -> Pattern {
+        Pattern::new(self.read_grid()).with_rule(self.rule)
+    }
+
+    /// Reads the `width` by `height` rectangle of cells with its top left
+    /// corner at `x`, `y` as a pattern with the simulation's rule, blocking
+    /// until the read completes.
+    ///
+    /// Panics if the rectangle doesn't fit in the grid.
+    pub fn read_region_pattern(&self, x: u32, y: u32, width: u32, height: u32) -> Pattern {
+        let cells = self.read_region(x, y, width, height);
+        Pattern::new(PackedGrid::from_cells(width, height, &cells)).with_rule(self.rule)
+    }
+
+    /// Returns the range of block indices covering a rectangle of cells, from
+    /// the first block of its top row to the last block of its bottom row.
+    ///
+    /// Panics if the rectangle is empty or doesn't fit in the grid.
+    fn region_span(&self, x: u32, y: u32, width: u32, height: u32) -> std::ops::Range<u32> {
+        let [grid_width, grid_height] = self.logical_grid_size;
+        assert!(
+            width > 0
+                && height > 0
+                && x.checked_add(width).is_some_and(|end| end <= grid_width)
+                && y.checked_add(height).is_some_and(|end| end <= grid_height),
+            "Region {width}x{height} at ({x}, {y}) doesn't fit in the {grid_width}x{grid_height} grid",
+        );
+
+        let physical_width = self.physical_grid_size[0];
+        let start = y * physical_width + x / 32;
+        let end = (y + height - 1) * physical_width + (x + width - 1) / 32;
+        start..end + 1
+    }
+
+    /// Starts computing statistics about the current generation on the GPU
+    /// without blocking, returning a future that resolves to the results.
+    ///
+    /// The reduction runs on the GPU and only the results are read back, which
+    /// is a few bytes plus any row or column counts requested in `options`, so
+    /// this is cheap enough to do every generation. See
+    /// [`Self::read_state_async`] for how the read progresses.
+    pub fn read_stats_async(&self, options: StatsOptions) -> StateReadback<GridStats> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Read Stats Encoder"),
+            });
+        let readback = self.encode_stats(&mut encoder, options);
+        self.queue.submit([encoder.finish()]);
+        readback.submitted()
+    }
+
+    /// Records computing statistics about the generation the simulation is on
+    /// into `encoder`, without submitting anything.
+    ///
+    /// This lets the statistics for several generations be recorded in
+    /// between calls to [`Self::encode_steps`] and submitted at once. Call
+    /// [`EncodedReadback::submitted`] after submitting `encoder` to start
+    /// reading the results back.
+    pub fn encode_stats(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        options: StatsOptions,
+    ) -> EncodedReadback<GridStats> {
+        let len = self.stats.len(options);
+        let stats = &self.stats;
+        let device = &self.device;
+        let current_state = self.current_state;
+        self.staging.lock().unwrap().encode_read_with(
+            device,
+            encoder,
+            len,
+            StatsPipeline::unpack(self.logical_grid_size, options),
+            |encoder, staging| {
+                stats.encode(device, encoder, current_state, options);
+                encoder.copy_buffer_to_buffer(
+                    stats.buffer(),
+                    0,
+                    staging,
+                    0,
+                    len as u64 * size_of::<u32>() as u64,
+                );
+            },
+        )
+    }
+
+    /// Computes statistics about the current generation on the GPU, blocking
+    /// until the results are read back.
+    pub fn read_stats(&self, options: StatsOptions) -> GridStats {
+        let readback = self.read_stats_async(options);
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .expect("Failed to poll device");
+
+        pollster::block_on(readback).expect("Failed to map staging buffer")
+    }
+
+    /// Counts the live cells in the current generation on the GPU, blocking
+    /// until the count is read back.
+    pub fn population(&self) -> u64 {
+        self.read_stats(StatsOptions::new()).population
     }
 
     /// Tells the GPU to copy the current state of the simulation to the read
@@ -308,7 +936,7 @@ impl LifeSimulation {
     /// This must be called before reading from the read buffer. Trying to read
     /// the state without calling this will yield old state data.
     pub fn encode_read(&self, encoder: &mut wgpu::CommandEncoder) {
-        let src_buffer = &self.state_bufs[(self.step % 2) as usize];
+        let src_buffer = &self.state_bufs[self.current_state];
         encoder.copy_buffer_to_buffer(
             src_buffer,
             0,
@@ -321,70 +949,191 @@ impl LifeSimulation {
     /// Reads the current grid state from the GPU, blocking until the read
     /// completes.
     pub fn read_state(&self) -> Vec<u8> {
-        // Have the GPU copy the current state to the read buffer.
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Read State Encoder"),
-            });
-        self.encode_read(&mut encoder);
-        self.queue.submit([encoder.finish()]);
+        let readback = self.read_state_async();
 
-        // Wait until the copy operation finishes.
+        // Waiting for the queue to finish also runs the map callback, so the
+        // readback is already complete after this.
         self.device
             .poll(wgpu::PollType::Wait)
             .expect("Failed to poll device");
 
-        // Read the contents of the read buffer.
-        // -------------------------------------
+        pollster::block_on(readback).expect("Failed to map staging buffer")
+    }
+}
 
-        let buf_slice = self.read_buf.slice(..);
+/// Checks that a `width` by `height` grid with `initial_state` can be simulated
+/// with `kernel`.
+fn validate_grid(
+    width: u32,
+    height: u32,
+    initial_state: &[u8],
+    kernel: Kernel,
+) -> Result<(), SimError> {
+    let num_cells = validate_dimensions([width, height], kernel)?;
+    if initial_state.len() != num_cells {
+        return Err(SimError::InvalidStateLength {
+            expected: num_cells,
+            actual: initial_state.len(),
+        });
+    }
 
-        let finished_flag = Arc::new(AtomicBool::new(false));
-        let ff_handle = finished_flag.clone();
+    Ok(())
+}
 
-        buf_slice.map_async(wgpu::MapMode::Read, move |result| {
-            result.expect("Failed to map read buffer");
-            ff_handle.store(true, Ordering::SeqCst);
-        });
+/// Checks that a grid of `grid_size` cells can be simulated with `kernel`,
+/// returning the number of cells in the grid.
+fn validate_dimensions(grid_size: [u32; 2], kernel: Kernel) -> Result<usize, SimError> {
+    let [width, height] = grid_size;
+    let generations = kernel.generations_per_pass();
+    if !(1..=Kernel::MAX_GENERATIONS).contains(&generations) {
+        return Err(SimError::InvalidGenerations(generations));
+    }
 
-        self.device
-            .poll(wgpu::PollType::Wait)
-            .expect("Failed to poll device");
+    // Cell indices are computed with `u32`s both here and in the shader, so the
+    // number of cells has to fit in one.
+    let num_cells = match width.checked_mul(height) {
+        Some(num_cells) if num_cells > 0 => num_cells as usize,
+        _ => return Err(SimError::InvalidDimensions { width, height }),
+    };
 
-        while !finished_flag.load(Ordering::SeqCst) {
-            std::thread::yield_now();
-        }
+    Ok(num_cells)
+}
+
+/// Compiles the simulation shader from `source` and creates the compute
+/// pipelines for `kernel`, i.e. the main pipeline and the single step pipeline
+/// if the kernel needs one.
+async fn create_pipelines(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    source: &str,
+    kernel: Kernel,
+) -> Result<(wgpu::ComputePipeline, Option<wgpu::ComputePipeline>), SimError> {
+    // Shader compilation and pipeline validation errors are reported through
+    // the device's error scopes rather than returned directly.
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let simulation_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Simulation Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Simulation Pipeline"),
+        layout: Some(pipeline_layout),
+        module: &simulation_shader,
+        entry_point: Some(kernel.entry_point()),
+        compilation_options: wgpu::PipelineCompilationOptions {
+            constants: &[("GENERATIONS", kernel.generations_per_pass() as f64)],
+            ..Default::default()
+        },
+        cache: None,
+    });
+
+    let single_step_pipeline = match kernel {
+        Kernel::Temporal { .. } => Some(device.create_compute_pipeline(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("Single Step Simulation Pipeline"),
+                layout: Some(pipeline_layout),
+                module: &simulation_shader,
+                entry_point: Some(Kernel::Tiled.entry_point()),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            },
+        )),
+        Kernel::Direct | Kernel::Tiled => None,
+    };
+
+    if let Some(err) = device.pop_error_scope().await {
+        return Err(SimError::Shader(err));
+    }
 
-        let view = buf_slice.get_mapped_range();
-        let raw_data = bytemuck::cast_slice::<_, u32>(&*view);
+    Ok((compute_pipeline, single_step_pipeline))
+}
 
-        // Convert the raw data to a byte array.
-        let byte_grid = unpack_grid(self.logical_grid_size, raw_data);
+#[cfg(feature = "hot-reload")]
+fn shader_modified_time() -> Option<std::time::SystemTime> {
+    std::fs::metadata(SHADER_PATH)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
 
-        // Release the read buffer.
-        drop(view);
-        self.read_buf.unmap();
+/// Returns the number of workgroups to dispatch in each dimension for
+/// [`Kernel::Direct`].
+///
+/// The blocks are processed as one flat list, but large grids need more
+/// workgroups than fit in a single dimension, so the workgroups wrap onto as
+/// many rows as needed.
+fn direct_dispatch_size(num_blocks: u32, max_per_dimension: u32) -> [u32; 2] {
+    let num_workgroups = num_blocks.div_ceil(WORKGROUP_SIZE);
+    let x = num_workgroups.min(max_per_dimension);
+    [x, num_workgroups.div_ceil(x)]
+}
 
-        byte_grid
+/// Checks that a grid of `physical_grid_size` blocks fits within the device
+/// limits when stepped with `kernel`.
+fn check_limits(
+    limits: &wgpu::Limits,
+    physical_grid_size: [u32; 2],
+    kernel: Kernel,
+) -> Result<(), SimError> {
+    let check = |limit, requested: u64, max: u64| {
+        if requested > max {
+            Err(SimError::LimitExceeded {
+                limit,
+                requested,
+                max,
+            })
+        } else {
+            Ok(())
+        }
+    };
+
+    let [width, height] = physical_grid_size;
+    let state_size = width as u64 * height as u64 * size_of::<u32>() as u64;
+    check("max_buffer_size", state_size, limits.max_buffer_size)?;
+    check(
+        "max_storage_buffer_binding_size",
+        state_size,
+        limits.max_storage_buffer_binding_size as u64,
+    )?;
+
+    let max_workgroups = limits.max_compute_workgroups_per_dimension;
+    let workgroups = match kernel {
+        Kernel::Direct => direct_dispatch_size(width * height, max_workgroups),
+
+        // The temporal kernel falls back to the tiled kernel for single steps,
+        // which uses smaller tiles and so needs more workgroups.
+        Kernel::Tiled | Kernel::Temporal { .. } => {
+            [width.div_ceil(TILE_SIZE[0]), height.div_ceil(TILE_SIZE[1])]
+        }
+    };
+    for count in workgroups {
+        check(
+            "max_compute_workgroups_per_dimension",
+            count as u64,
+            max_workgroups as u64,
+        )?;
     }
+
+    Ok(())
 }
 
-/// Calcuate the size of the logical grid, and packs the initial state into a
+/// Calcuate the size of the physical grid, and packs the initial state into a
 /// vector of `u32`s.
-pub fn pack_grid(grid_size: u32, initial_state: &[u8]) -> (Vec<u32>, [u32; 2]) {
-    assert_eq!(initial_state.len(), (grid_size * grid_size) as usize);
+///
+/// `grid_size` is the width and height of the logical grid in cells.
+pub fn pack_grid(grid_size: [u32; 2], initial_state: &[u8]) -> (Vec<u32>, [u32; 2]) {
+    let [width, height] = grid_size;
+    assert_eq!(initial_state.len(), (width * height) as usize);
 
     // Calculate the width and height in blocks.
-    let block_width = grid_size.div_ceil(32);
-    let block_height = grid_size;
+    let block_width = width.div_ceil(32);
+    let block_height = height;
     let num_blocks = block_width * block_height;
 
     let mut packed_state = vec![0u32; num_blocks as usize];
-    for x in 0..grid_size {
-        for y in 0..grid_size {
-            let cell_index = y * grid_size + x;
-            let state = initial_state[cell_index as usize] as u32;
+    for x in 0..width {
+        for y in 0..height {
+            let cell_index = y * width + x;
+            let state = (initial_state[cell_index as usize] != 0) as u32;
 
             let block_index = block_width * y + x / 32;
             let bit_index = x % 32;
@@ -398,14 +1147,18 @@ pub fn pack_grid(grid_size: u32, initial_state: &[u8]) -> (Vec<u32>, [u32; 2]) {
     (packed_state, [block_width, block_height])
 }
 
-pub fn unpack_grid(grid_size: u32, packed_state: &[u32]) -> Vec<u8> {
-    let mut unpacked_state = vec![0u8; (grid_size * grid_size) as usize];
+/// Unpacks a grid of `u32` blocks into one byte per cell.
+///
+/// `grid_size` is the width and height of the logical grid in cells.
+pub fn unpack_grid(grid_size: [u32; 2], packed_state: &[u32]) -> Vec<u8> {
+    let [width, height] = grid_size;
+    let mut unpacked_state = vec![0u8; (width * height) as usize];
 
-    let block_width = grid_size.div_ceil(32);
+    let block_width = width.div_ceil(32);
 
-    for x in 0..grid_size {
-        for y in 0..grid_size {
-            let cell_index = y * grid_size + x;
+    for x in 0..width {
+        for y in 0..height {
+            let cell_index = y * width + x;
 
             let block_index = block_width * y + x / 32;
             let bit_index = x % 32;
@@ -1,29 +1,55 @@
 use std::{
+    error::Error,
     sync::Arc,
     time::{Duration, Instant},
 };
-use wgpu::util::DeviceExt;
-use wgpu_gol::LifeSimulation;
+
+use clap::Parser;
+use wgpu_gol::{
+    Camera, LifeSimulation, PackedGrid, Renderer, SimError, SimulationConfig, StatsOptions,
+};
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::Key,
     window::{Window, WindowId},
 };
 
-#[rustfmt::skip]
-static VERTICES: &[f32] = &[
-  -0.8, -0.8,
-   0.8, -0.8,
-   0.8,  0.8,
+#[path = "bin/cli/mod.rs"]
+mod cli;
+
+/// The most generations run in a single frame. If the simulation falls
+/// further behind than this, e.g. because the GPU can't keep up with the tick
+/// rate, the missed generations are skipped rather than piling up.
+const MAX_GENERATIONS_PER_FRAME: u128 = 1000;
+
+/// How much each line of scrolling zooms in or out by.
+const ZOOM_PER_LINE: f32 = 1.25;
 
-  -0.8, -0.8,
-   0.8,  0.8,
-  -0.8,  0.8,
-];
+/// How many pixels of scrolling on a touchpad count as a line.
+const PIXELS_PER_LINE: f32 = 40.0;
 
-const GRID_SIZE: u32 = 64;
-const TICK_INTERVAL_MS: u64 = 1000 / 10;
+/// The furthest the camera can zoom in, with each cell 64 pixels wide.
+const MIN_CELLS_PER_PIXEL: f32 = 1.0 / 64.0;
+
+/// The number of cells left around the pattern when fitting the camera to it.
+const FIT_MARGIN: f32 = 2.0;
+
+#[derive(Debug, Parser)]
+#[command(
+    about = "Runs the Game of Life in a window",
+    after_help = "Drag to pan, scroll to zoom, press F to fit the view to the pattern or G to \
+        fit the whole grid."
+)]
+struct Args {
+    #[command(flatten)]
+    sim: cli::SimArgs,
+
+    /// Number of generations to run per second.
+    #[arg(long, default_value_t = 10.0, value_parser = parse_tick_rate)]
+    tick_rate: f64,
+}
 
 struct State {
     sim: LifeSimulation,
@@ -33,26 +59,57 @@ struct State {
     size: winit::dpi::PhysicalSize<u32>,
     surface: wgpu::Surface<'static>,
     surface_format: wgpu::TextureFormat,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    last_tick: Instant,
+    renderer: Renderer,
+    camera: Camera,
+
+    // The last position of the cursor in pixels, and whether the view is
+    // being dragged.
+    cursor: [f32; 2],
+    dragging: bool,
+
+    tick_interval: Duration,
+
+    // Time that has passed but hasn't been simulated yet, always less than
+    // `tick_interval` after an update.
+    accumulator: Duration,
+    last_update: Instant,
 }
 
 impl State {
-    async fn new(window: Arc<Window>) -> Self {
-        // Create a random initial state for the simulation.
-        let num_cells = (GRID_SIZE * GRID_SIZE) as usize;
-        let mut init_state = vec![0; num_cells as usize];
-
-        for i in 0..num_cells {
-            init_state[i] = rand::random::<u8>() % 2;
-        }
+    async fn new(
+        window: Arc<Window>,
+        grid: &PackedGrid,
+        sim_config: SimulationConfig,
+        tick_interval: Duration,
+    ) -> Result<Self, Box<dyn Error>> {
+        // Create the device ourselves so that we can pick an adapter that can
+        // present to the window, then run the simulation on the same device.
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: sim_config.backends,
+            ..Default::default()
+        });
+        let surface = instance.create_surface(window.clone())?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: sim_config.power_preference,
+                force_fallback_adapter: sim_config.force_fallback_adapter,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .map_err(SimError::NoAdapter)?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                required_features: sim_config.required_features,
+                required_limits: sim_config.required_limits.clone(),
+                ..Default::default()
+            })
+            .await
+            .map_err(SimError::RequestDevice)?;
 
-        let sim = LifeSimulation::new(GRID_SIZE, &init_state).await;
+        let sim = LifeSimulation::from_device_with_grid(&device, &queue, grid, sim_config).await?;
 
         let size = window.inner_size();
-        let surface = sim.instance.create_surface(window.clone()).unwrap();
-        let surface_caps = surface.get_capabilities(&sim.adapter);
+        let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps.formats[0];
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -65,63 +122,10 @@ impl State {
             desired_maximum_frame_latency: 2,
         };
 
-        let render_shader = sim
-            .device
-            .create_shader_module(wgpu::include_wgsl!("shaders.wgsl"));
-        let render_pipeline = sim
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&sim.pipeline_layout),
-
-                vertex: wgpu::VertexState {
-                    module: &render_shader,
-                    entry_point: Some("vertex_main"),
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![0 => Float32x2],
-                    }],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                },
-
-                fragment: Some(wgpu::FragmentState {
-                    module: &render_shader,
-                    entry_point: Some("fragment_main"),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                }),
-
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
-            });
-
-        let vertex_buffer = sim
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(VERTICES),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
+        // Render to an sRGB view of the surface so that the palette is
+        // gamma correct.
+        let renderer = Renderer::new(&sim, surface_format.add_srgb_suffix());
+        let camera = Camera::fit_grid(&sim, [size.width, size.height]);
 
         let mut state = State {
             sim,
@@ -130,15 +134,19 @@ impl State {
             size,
             surface,
             surface_format,
-            render_pipeline,
-            vertex_buffer,
-            last_tick: Instant::now(),
+            renderer,
+            camera,
+            cursor: [0.0; 2],
+            dragging: false,
+            tick_interval,
+            accumulator: Duration::ZERO,
+            last_update: Instant::now(),
         };
 
         // Configure surface for the first time
         state.configure_surface();
 
-        state
+        Ok(state)
     }
 
     fn get_window(&self) -> &Window {
@@ -160,23 +168,92 @@ impl State {
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // Surfaces can't be empty, so keep the old size while the window is
+        // minimized.
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
+
         self.size = new_size;
 
         // reconfigure the surface
         self.configure_surface();
     }
 
-    fn render(&mut self) {
-        // Do nothing if not enough time has passed since last tick.
-        //
-        // TODO: We should still render the current state even if not enough
-        // time has passed to run another tick, that way we still render quickly
-        // in response to e.g. the window being resized.
-        if self.last_tick.elapsed() < Duration::from_millis(TICK_INTERVAL_MS) {
-            return;
+    fn target_size(&self) -> [u32; 2] {
+        [self.size.width, self.size.height]
+    }
+
+    /// Moves the cursor to `position`, dragging the view along with it if the
+    /// mouse button is held.
+    fn move_cursor(&mut self, position: [f32; 2]) {
+        if self.dragging {
+            self.camera
+                .pan([position[0] - self.cursor[0], position[1] - self.cursor[1]]);
         }
+        self.cursor = position;
+    }
 
-        self.last_tick = Instant::now();
+    /// Zooms in by `lines` lines of scrolling, or out if negative, keeping the
+    /// cell under the cursor in place.
+    fn zoom(&mut self, lines: f32) {
+        // Don't zoom out much further than it takes to see the whole grid.
+        let max_cells_per_pixel =
+            Camera::fit_grid(&self.sim, self.target_size()).cells_per_pixel * 4.0;
+        let cells_per_pixel = (self.camera.cells_per_pixel / ZOOM_PER_LINE.powf(lines)).clamp(
+            MIN_CELLS_PER_PIXEL,
+            max_cells_per_pixel.max(MIN_CELLS_PER_PIXEL),
+        );
+
+        let factor = self.camera.cells_per_pixel / cells_per_pixel;
+        self.camera.zoom_at(factor, self.cursor, self.target_size());
+    }
+
+    /// Fits the view to the live cells, or to the whole grid if there aren't
+    /// any.
+    fn fit_pattern(&mut self) {
+        let stats = self.sim.read_stats(StatsOptions::new());
+        self.camera = match stats.bounds {
+            Some(bounds) => Camera::fit(
+                bounds.x as f32 - FIT_MARGIN,
+                bounds.y as f32 - FIT_MARGIN,
+                bounds.width as f32 + FIT_MARGIN * 2.0,
+                bounds.height as f32 + FIT_MARGIN * 2.0,
+                self.target_size(),
+            ),
+            None => Camera::fit_grid(&self.sim, self.target_size()),
+        };
+        self.camera.cells_per_pixel = self.camera.cells_per_pixel.max(MIN_CELLS_PER_PIXEL);
+    }
+
+    /// Encodes the generations that are due since the last update, at a fixed
+    /// rate of one every `tick_interval` no matter how often this is called.
+    ///
+    /// At high tick rates this runs several generations per frame, and at low
+    /// rates most frames run none.
+    fn update(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let now = Instant::now();
+        self.accumulator += now - self.last_update;
+        self.last_update = now;
+
+        let tick = self.tick_interval.as_nanos().max(1);
+        let due = self.accumulator.as_nanos() / tick;
+        self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % tick) as u64);
+
+        let generations = due.min(MAX_GENERATIONS_PER_FRAME);
+        if generations > 0 {
+            self.sim.encode_steps(encoder, generations as u64);
+        }
+    }
+
+    /// Runs any generations that are due and draws the current state.
+    fn render(&mut self) {
+        #[cfg(feature = "hot-reload")]
+        match self.sim.reload_shader() {
+            Ok(true) => println!("Reloaded simulation shader"),
+            Ok(false) => {}
+            Err(err) => eprintln!("{err}"),
+        }
 
         // Create texture view.
         let surface_texture = self
@@ -200,31 +277,11 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        self.sim.encode_compute_pass(&mut encoder);
-
-        // Create the renderpass which will clear the screen.
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &texture_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-        });
-
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.sim.bind_groups[(self.sim.step % 2) as usize], &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.draw(0..VERTICES.len() as u32 / 2, 0..self.sim.num_cells as u32);
+        self.update(&mut encoder);
 
-        // End the renderpass.
-        drop(render_pass);
+        self.renderer.camera = Some(self.camera);
+        self.renderer
+            .encode(&self.sim, &mut encoder, &texture_view, self.target_size());
 
         // Submit the command in the queue to execute
         self.sim.queue.submit([encoder.finish()]);
@@ -233,9 +290,18 @@ impl State {
     }
 }
 
-#[derive(Default)]
 struct App {
     state: Option<State>,
+
+    // The error that stopped the app from starting, reported once the event
+    // loop exits.
+    error: Option<Box<dyn Error>>,
+
+    // The starting state of the simulation, used once the window has been
+    // created.
+    grid: PackedGrid,
+    sim_config: SimulationConfig,
+    tick_interval: Duration,
 }
 
 impl ApplicationHandler for App {
@@ -246,14 +312,28 @@ impl ApplicationHandler for App {
                 .unwrap(),
         );
 
-        let state = pollster::block_on(State::new(window.clone()));
-        self.state = Some(state);
-
-        window.request_redraw();
+        let state = pollster::block_on(State::new(
+            window.clone(),
+            &self.grid,
+            self.sim_config.clone(),
+            self.tick_interval,
+        ));
+        match state {
+            Ok(state) => {
+                self.state = Some(state);
+                window.request_redraw();
+            }
+            Err(err) => {
+                self.error = Some(err);
+                event_loop.exit();
+            }
+        }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
-        let state = self.state.as_mut().unwrap();
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
@@ -267,22 +347,57 @@ impl ApplicationHandler for App {
                 state.get_window().request_redraw();
             }
             WindowEvent::Resized(size) => {
-                // Reconfigures the size of the surface. We do not re-render
-                // here as this event is always followed up by redraw request.
+                // Reconfigures the size of the surface, then redraws so that
+                // the window never shows a stretched or stale frame. The
+                // camera keeps its center and zoom, so the cells stay square.
                 state.resize(size);
+                state.get_window().request_redraw();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                state.move_cursor([position.x as f32, position.y as f32]);
             }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                state.dragging = button_state == ElementState::Pressed;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+                state.zoom(lines);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match key.to_lowercase().as_str() {
+                "f" => state.fit_pattern(),
+                "g" => state.camera = Camera::fit_grid(&state.sim, state.target_size()),
+                _ => {}
+            },
             _ => (),
         }
     }
 }
 
-fn main() {
+fn main() -> Result<(), Box<dyn Error>> {
     // wgpu uses `log` for all of our logging, so we initialize a logger with the `env_logger` crate.
     //
     // To change the log level, set the `RUST_LOG` environment variable. See the `env_logger`
     // documentation for more information.
     env_logger::init();
 
+    let args = Args::parse();
+    let (grid, sim_config) = args.sim.load()?;
+
     let event_loop = EventLoop::new().unwrap();
 
     // When the current loop iteration finishes, immediately begin a new
@@ -291,6 +406,26 @@ fn main() {
     // possible, like games.
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::default();
-    event_loop.run_app(&mut app).unwrap();
+    let mut app = App {
+        state: None,
+        error: None,
+        grid,
+        sim_config,
+        tick_interval: Duration::from_secs_f64(1.0 / args.tick_rate),
+    };
+    event_loop.run_app(&mut app)?;
+
+    match app.error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn parse_tick_rate(s: &str) -> Result<f64, String> {
+    match s.parse() {
+        Ok(rate) if rate > 0.0 && f64::is_finite(rate) => Ok(rate),
+        _ => Err(format!(
+            "expected a positive number of ticks per second, got {s:?}"
+        )),
+    }
 }
@@ -0,0 +1,395 @@
+use std::{fmt, str::FromStr};
+
+use crate::{HashLife, PackedGrid, ParseRuleError, Rule, hashlife};
+
+mod life;
+mod plaintext;
+mod rle;
+
+/// A finite pattern of live cells, e.g. one loaded from a pattern file.
+///
+/// Patterns are read from and written to text with [`Pattern::parse`] and
+/// [`Pattern::write`], or the `from_*` and `to_*` methods for each format,
+/// and can be placed into a running simulation with
+/// [`LifeSimulation::write_pattern`].
+///
+/// [`LifeSimulation::write_pattern`]: crate::LifeSimulation::write_pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    /// The cells of the pattern, with the top left cell at `(0, 0)`.
+    pub grid: PackedGrid,
+
+    /// The rule the pattern is meant to run under, if the file specified one.
+    pub rule: Option<Rule>,
+
+    /// The name of the pattern, if the file specified one.
+    pub name: Option<String>,
+
+    /// Any comment lines in the file, without the comment markers.
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    /// Creates a pattern from `grid` with no rule, name or comments.
+    pub fn new(grid: PackedGrid) -> Self {
+        Self {
+            grid,
+            rule: None,
+            name: None,
+            comments: Vec::new(),
+        }
+    }
+
+    /// Sets the rule the pattern is meant to run under.
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rule = Some(rule);
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.grid.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.grid.height()
+    }
+
+    /// Parses a pattern in any of the supported formats, detecting the format
+    /// from the contents of `s`.
+    pub fn parse(s: &str) -> Result<Self, ParsePatternError> {
+        Self::parse_as(s, PatternFormat::detect(s))
+    }
+
+    /// Parses a pattern in `format`.
+    pub fn parse_as(s: &str, format: PatternFormat) -> Result<Self, ParsePatternError> {
+        match format {
+            PatternFormat::Rle => Self::from_rle(s),
+            PatternFormat::Plaintext => Self::from_plaintext(s),
+            PatternFormat::Life105 => Self::from_life_105(s),
+            PatternFormat::Life106 => Self::from_life_106(s),
+            PatternFormat::Macrocell => Self::from_macrocell(s),
+        }
+    }
+
+    /// Writes the pattern in `format`.
+    pub fn write(&self, format: PatternFormat) -> String {
+        match format {
+            PatternFormat::Rle => self.to_rle(),
+            PatternFormat::Plaintext => self.to_plaintext(),
+            PatternFormat::Life105 => self.to_life_105(),
+            PatternFormat::Life106 => self.to_life_106(),
+            PatternFormat::Macrocell => self.to_macrocell(),
+        }
+    }
+
+    /// Parses a pattern in the run length encoded (RLE) format.
+    ///
+    /// The `x = , y = , rule =` header sets the size of the pattern and its
+    /// rule. If the header is missing, the pattern is made just large enough
+    /// to hold its cells. `#N` lines set the name, and `#C` or `#c` lines are
+    /// kept as comments.
+    pub fn from_rle(s: &str) -> Result<Self, ParsePatternError> {
+        rle::parse(s)
+    }
+
+    /// Writes the pattern in the run length encoded (RLE) format, including
+    /// the header and any name and comments.
+    pub fn to_rle(&self) -> String {
+        rle::write(self)
+    }
+
+    /// Parses a pattern in the plaintext format, with `O` or `*` for live
+    /// cells and `.` for dead cells.
+    ///
+    /// The pattern is as wide as its longest line. A `!Name:` line sets the
+    /// name, and any other lines starting with `!` are kept as comments.
+    pub fn from_plaintext(s: &str) -> Result<Self, ParsePatternError> {
+        plaintext::parse(s)
+    }
+
+    /// Writes the pattern in the plaintext format, including any name and
+    /// comments.
+    pub fn to_plaintext(&self) -> String {
+        plaintext::write(self)
+    }
+
+    /// Parses a pattern in the Life 1.05 format, made of `#P` blocks of `*`
+    /// and `.` cells.
+    ///
+    /// `#D` lines are kept as comments, and `#N` or `#R` lines set the rule.
+    /// The pattern is cropped to its live cells.
+    pub fn from_life_105(s: &str) -> Result<Self, ParsePatternError> {
+        life::parse_105(s)
+    }
+
+    /// Writes the pattern in the Life 1.05 format. The name is written as the
+    /// first `#D` line, since the format has no separate field for it.
+    pub fn to_life_105(&self) -> String {
+        life::write_105(self)
+    }
+
+    /// Parses a pattern in the Life 1.06 format, which lists the coordinates
+    /// of each live cell.
+    ///
+    /// The pattern is cropped to its live cells.
+    pub fn from_life_106(s: &str) -> Result<Self, ParsePatternError> {
+        life::parse_106(s)
+    }
+
+    /// Writes the pattern in the Life 1.06 format. Only the live cells are
+    /// written, so the rule, name and comments are lost.
+    pub fn to_life_106(&self) -> String {
+        life::write_106(self)
+    }
+
+    /// Parses a pattern in Golly's macrocell format, cropped to its live
+    /// cells.
+    ///
+    /// Macrocell files can describe patterns far too large to flatten into a
+    /// grid, in which case this returns [`ParsePatternError::TooLarge`]. Use
+    /// [`HashLife::from_macrocell`] to work with those directly.
+    pub fn from_macrocell(s: &str) -> Result<Self, ParsePatternError> {
+        // The pattern is never advanced here, so any rule will do.
+        let hashlife = HashLife::from_macrocell_with_any_rule(s)?;
+        if let Some((_, [width, height])) = hashlife.bounds() {
+            let too_large = width
+                .checked_mul(height)
+                .is_none_or(|cells| cells > u32::MAX as u64);
+            if too_large {
+                return Err(ParsePatternError::TooLarge { width, height });
+            }
+        }
+
+        Ok(hashlife.to_pattern().0)
+    }
+
+    /// Writes the pattern in Golly's macrocell format, with the rule and no
+    /// name or comments.
+    ///
+    /// Rules with `B0` are written as is, and can be read back with
+    /// [`Pattern::from_macrocell`] but not [`HashLife::from_macrocell`].
+    pub fn to_macrocell(&self) -> String {
+        HashLife::from_pattern_with_any_rule(self).to_macrocell()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = ParsePatternError;
+
+    /// Parses a pattern in any of the supported formats, see
+    /// [`Pattern::parse`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// A text format that patterns can be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatternFormat {
+    /// The run length encoded format, usually saved as `.rle`.
+    Rle,
+
+    /// The plaintext format, usually saved as `.cells`.
+    Plaintext,
+
+    /// The Life 1.05 format, usually saved as `.lif` or `.life`.
+    Life105,
+
+    /// The Life 1.06 format, usually saved as `.lif` or `.life`.
+    Life106,
+
+    /// Golly's macrocell format, usually saved as `.mc`.
+    Macrocell,
+}
+
+impl PatternFormat {
+    /// Guesses the format of a pattern from its contents.
+    ///
+    /// The Life and macrocell formats are identified by their `#Life` and
+    /// `[M2]` headers. Otherwise,
+    /// the pattern is assumed to be plaintext if it starts with a `!` comment
+    /// or only uses the characters allowed in plaintext cells, and RLE if not.
+    pub fn detect(s: &str) -> Self {
+        let Some(first) = s.lines().map(str::trim).find(|line| !line.is_empty()) else {
+            return Self::Rle;
+        };
+
+        if first.starts_with(hashlife::MACROCELL_HEADER) {
+            Self::Macrocell
+        } else if first.starts_with(life::LIFE_105_HEADER) {
+            Self::Life105
+        } else if first.starts_with(life::LIFE_106_HEADER) {
+            Self::Life106
+        } else if first.starts_with('!') || first.chars().all(|c| matches!(c, '.' | 'O' | '*')) {
+            Self::Plaintext
+        } else {
+            Self::Rle
+        }
+    }
+
+    /// Returns the format usually saved with the file extension `extension`,
+    /// e.g. `"rle"` or `"cells"`.
+    ///
+    /// Life 1.05 and Life 1.06 files share their extensions, so this returns
+    /// Life 1.06 for them.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "rle" => Some(Self::Rle),
+            "cells" => Some(Self::Plaintext),
+            "lif" | "life" => Some(Self::Life106),
+            "mc" => Some(Self::Macrocell),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when parsing a [`Pattern`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePatternError {
+    /// The header line giving the size and rule of the pattern is malformed.
+    InvalidHeader(String),
+
+    /// The rule given by the pattern couldn't be parsed.
+    InvalidRule(ParseRuleError),
+
+    /// A character that isn't allowed by the format appeared in the cells of
+    /// the pattern.
+    InvalidCharacter { line: usize, character: char },
+
+    /// A line that should hold a pair of coordinates doesn't.
+    InvalidCoordinates { line: usize },
+
+    /// The pattern has a live cell outside of the size given in its header.
+    OutOfBounds { x: u64, y: u64 },
+
+    /// A node in a macrocell file is malformed, or refers to a child that
+    /// doesn't exist or has the wrong size.
+    InvalidNode { line: usize },
+
+    /// The pattern uses a rule that can't be run by the engine it's read
+    /// into, i.e. one with `B0` in a macrocell file read by [`HashLife`].
+    UnsupportedRule(Rule),
+
+    /// The pattern is too large to fit in a grid.
+    TooLarge { width: u64, height: u64 },
+}
+
+impl fmt::Display for ParsePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader(line) => write!(f, "invalid pattern header {line:?}"),
+            Self::InvalidRule(err) => write!(f, "invalid rule in pattern: {err}"),
+            Self::InvalidCharacter { line, character } => {
+                write!(f, "unexpected character {character:?} on line {line}")
+            }
+            Self::InvalidCoordinates { line } => write!(f, "invalid coordinates on line {line}"),
+            Self::InvalidNode { line } => write!(f, "invalid node on line {line}"),
+            Self::UnsupportedRule(rule) => write!(f, "rule {rule} is unsupported"),
+            Self::OutOfBounds { x, y } => write!(
+                f,
+                "cell ({x}, {y}) is outside of the size given in the pattern header",
+            ),
+            Self::TooLarge { width, height } => {
+                write!(f, "pattern size {width}x{height} is too large")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParsePatternError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidRule(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Builds a grid just large enough to hold `cells`, or `width` by `height` if
+/// a size is given.
+///
+/// `cells` may contain duplicates. Returns an error if a cell lies outside of
+/// the given size, or the grid would be too large.
+fn grid_from_cells(
+    size: Option<[u64; 2]>,
+    cells: &[[u64; 2]],
+) -> Result<PackedGrid, ParsePatternError> {
+    let runs = cells.iter().map(|&[x, y]| [x, y, 1]).collect::<Vec<_>>();
+    grid_from_runs(size, &runs)
+}
+
+/// Like [`grid_from_cells`], but for runs of `length` live cells starting at
+/// `[x, y, length]`, so that long runs don't need a cell each.
+fn grid_from_runs(
+    size: Option<[u64; 2]>,
+    runs: &[[u64; 3]],
+) -> Result<PackedGrid, ParsePatternError> {
+    let [width, height] = match size {
+        Some(size) => size,
+        None => {
+            let [mut width, mut height] = [0, 0];
+            for &[x, y, length] in runs {
+                let (Some(end_x), Some(end_y)) = (x.checked_add(length), y.checked_add(1)) else {
+                    return Err(ParsePatternError::TooLarge {
+                        width: x.saturating_add(length),
+                        height: y.saturating_add(1),
+                    });
+                };
+                width = width.max(end_x);
+                height = height.max(end_y);
+            }
+            [width, height]
+        }
+    };
+
+    let outside = |&&[x, y, length]: &&[u64; 3]| x.saturating_add(length) > width || y >= height;
+    if let Some(&[x, y, _]) = runs.iter().find(outside) {
+        // Report the first cell of the run that's outside.
+        let x = if y < height { x.max(width) } else { x };
+        return Err(ParsePatternError::OutOfBounds { x, y });
+    }
+
+    let too_large = || ParsePatternError::TooLarge { width, height };
+    let grid_width = u32::try_from(width).map_err(|_| too_large())?;
+    let grid_height = u32::try_from(height).map_err(|_| too_large())?;
+    grid_width.checked_mul(grid_height).ok_or_else(too_large)?;
+
+    let mut grid = PackedGrid::new(grid_width, grid_height);
+    for &[x, y, length] in runs {
+        for x in x..x + length {
+            grid.set(x as u32, y as u32, true);
+        }
+    }
+
+    Ok(grid)
+}
+
+/// Builds a grid just large enough to hold `cells`, which may have any
+/// origin, by moving the top left live cell to `(0, 0)`.
+fn grid_from_offsets(cells: &[[i64; 2]]) -> Result<PackedGrid, ParsePatternError> {
+    let [min_x, min_y] = cells.iter().fold([i64::MAX; 2], |[min_x, min_y], &[x, y]| {
+        [min_x.min(x), min_y.min(y)]
+    });
+
+    let cells = cells
+        .iter()
+        .map(|&[x, y]| [x.abs_diff(min_x), y.abs_diff(min_y)])
+        .collect::<Vec<_>>();
+    grid_from_cells(None, &cells)
+}
+
+/// Splits a `#` line, without the `#`, into the character saying what kind of
+/// line it is and the rest of the line.
+pub(crate) fn split_directive(line: &str) -> (&str, &str) {
+    let index = line.chars().next().map_or(0, char::len_utf8);
+    line.split_at(index)
+}
+
+/// Parses the rule given by a pattern file.
+///
+/// Golly appends the bounded grid to the rule, e.g. `B3/S23:T64,64`, which
+/// isn't part of the rule itself and is ignored.
+pub(crate) fn parse_rule(rule: &str) -> Result<Rule, ParsePatternError> {
+    let rule = rule.split_once(':').map_or(rule, |(rule, _)| rule);
+    rule.parse().map_err(ParsePatternError::InvalidRule)
+}
@@ -0,0 +1,66 @@
+//! The plaintext format, usually saved with a `.cells` extension. See
+//! <https://conwaylife.com/wiki/Plaintext>.
+
+use std::fmt::Write;
+
+use super::{ParsePatternError, Pattern, grid_from_cells};
+
+pub(super) fn parse(s: &str) -> Result<Pattern, ParsePatternError> {
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut cells = Vec::new();
+    let mut width = 0;
+    let mut height = 0;
+
+    for (index, line) in s.lines().enumerate() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(text) => name = Some(text.trim().to_owned()),
+                None => comments.push(comment.trim().to_owned()),
+            }
+            continue;
+        }
+
+        for (x, character) in line.chars().enumerate() {
+            match character {
+                '.' => {}
+                'O' | '*' => cells.push([x as u64, height]),
+                character => {
+                    return Err(ParsePatternError::InvalidCharacter {
+                        line: index + 1,
+                        character,
+                    });
+                }
+            }
+        }
+
+        width = width.max(line.chars().count() as u64);
+        height += 1;
+    }
+
+    Ok(Pattern {
+        grid: grid_from_cells(Some([width, height]), &cells)?,
+        rule: None,
+        name,
+        comments,
+    })
+}
+
+pub(super) fn write(pattern: &Pattern) -> String {
+    let mut output = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(output, "!Name: {name}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(output, "!{comment}").unwrap();
+    }
+
+    let grid = &pattern.grid;
+    for y in 0..grid.height() {
+        output.extend((0..grid.width()).map(|x| if grid.get(x, y) { 'O' } else { '.' }));
+        output.push('\n');
+    }
+
+    output
+}
@@ -0,0 +1,259 @@
+//! The run length encoded (RLE) format, the most common format for sharing
+//! patterns. See <https://conwaylife.com/wiki/Run_Length_Encoded>.
+
+use std::fmt::Write;
+
+use super::{ParsePatternError, Pattern, grid_from_runs, parse_rule, split_directive};
+use crate::Rule;
+
+/// The longest line written in the encoded cells, as recommended by the format.
+const MAX_LINE_LENGTH: usize = 70;
+
+pub(super) fn parse(s: &str) -> Result<Pattern, ParsePatternError> {
+    let mut name = None;
+    let mut comments = Vec::new();
+    let mut size = None;
+    let mut rule = None;
+    let mut cells = CellParser::default();
+
+    for (index, line) in s.lines().enumerate() {
+        let line = line.trim();
+
+        // Comments and the header can only come before the cells.
+        if !cells.started {
+            if let Some(comment) = line.strip_prefix('#') {
+                let (kind, text) = split_directive(comment);
+                match kind {
+                    "N" => name = Some(text.trim().to_owned()),
+                    "C" | "c" => comments.push(text.trim().to_owned()),
+                    _ => {}
+                }
+                continue;
+            }
+
+            if line.starts_with('x') {
+                (size, rule) = parse_header(line)?;
+                continue;
+            }
+        }
+
+        if cells.parse_line(index, line)? {
+            break;
+        }
+    }
+
+    Ok(Pattern {
+        grid: grid_from_runs(size, &cells.runs)?,
+        rule,
+        name,
+        comments,
+    })
+}
+
+/// Parses a header line like `x = 3, y = 3, rule = B3/S23`, returning the size
+/// and rule.
+fn parse_header(line: &str) -> Result<(Option<[u64; 2]>, Option<Rule>), ParsePatternError> {
+    let invalid = || ParsePatternError::InvalidHeader(line.to_owned());
+
+    // The rule comes last and can contain commas itself, e.g. Golly's bounded
+    // grids like `B3/S23:T64,64`.
+    let (fields, rule_field) = match line.find("rule") {
+        Some(index) => (&line[..index], Some(&line[index..])),
+        None => (line, None),
+    };
+
+    let [mut width, mut height] = [None; 2];
+    for field in fields.split(',').filter(|field| !field.trim().is_empty()) {
+        let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = Some(value.parse().map_err(|_| invalid())?),
+            "y" => height = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        }
+    }
+
+    let rule = match rule_field {
+        Some(field) => {
+            let (_, value) = field.split_once('=').ok_or_else(invalid)?;
+            Some(parse_rule(value.trim())?)
+        }
+        None => None,
+    };
+
+    let size = [width.ok_or_else(invalid)?, height.ok_or_else(invalid)?];
+    Ok((Some(size), rule))
+}
+
+/// Decodes the runs of cells following the header.
+#[derive(Default)]
+struct CellParser {
+    /// Whether any cells have been parsed yet.
+    started: bool,
+
+    /// The runs of live cells found so far, as `[x, y, length]`. Runs aren't
+    /// expanded into cells until they've been checked against the size of
+    /// the grid, since a short line can describe billions of cells.
+    runs: Vec<[u64; 3]>,
+
+    /// The position of the next cell. Positions saturate rather than
+    /// overflowing, and live cells past the end of the grid are reported when
+    /// the grid is built.
+    x: u64,
+    y: u64,
+
+    /// The run count that has been read but not applied to a tag yet. Counts
+    /// can be split across lines.
+    count: Option<u64>,
+}
+
+impl CellParser {
+    /// Parses one line of encoded cells, returning `true` if the line
+    /// contained the `!` marking the end of the pattern.
+    fn parse_line(&mut self, index: usize, line: &str) -> Result<bool, ParsePatternError> {
+        for character in line.chars() {
+            if character.is_whitespace() {
+                continue;
+            }
+
+            self.started = true;
+            if let Some(digit) = character.to_digit(10) {
+                let count = self.count.unwrap_or(0);
+                self.count = Some(count.saturating_mul(10).saturating_add(digit as u64));
+                continue;
+            }
+
+            let run = self.count.take().unwrap_or(1);
+            match character {
+                'b' | '.' => self.x = self.x.saturating_add(run),
+                '$' => {
+                    self.x = 0;
+                    self.y = self.y.saturating_add(run);
+                }
+                '!' => return Ok(true),
+
+                // Multi-state patterns use other letters for the different
+                // live states, which all count as alive here.
+                c if c.is_ascii_alphabetic() => {
+                    if run > 0 {
+                        self.runs.push([self.x, self.y, run]);
+                    }
+                    self.x = self.x.saturating_add(run);
+                }
+
+                character => {
+                    return Err(ParsePatternError::InvalidCharacter {
+                        line: index + 1,
+                        character,
+                    });
+                }
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+pub(super) fn write(pattern: &Pattern) -> String {
+    let mut output = String::new();
+    if let Some(name) = &pattern.name {
+        writeln!(output, "#N {name}").unwrap();
+    }
+    for comment in &pattern.comments {
+        writeln!(output, "#C {comment}").unwrap();
+    }
+
+    write!(output, "x = {}, y = {}", pattern.width(), pattern.height()).unwrap();
+    if let Some(rule) = pattern.rule {
+        write!(output, ", rule = {rule}").unwrap();
+    }
+    output.push('\n');
+
+    let mut writer = RunWriter::new(&mut output);
+    let grid = &pattern.grid;
+    for y in 0..grid.height() {
+        let mut x = 0;
+        while x < grid.width() {
+            let alive = grid.get(x, y);
+            let start = x;
+            while x < grid.width() && grid.get(x, y) == alive {
+                x += 1;
+            }
+
+            // Dead cells at the end of a row are implied by the end of line.
+            if alive || x < grid.width() {
+                writer.push(x - start, if alive { 'o' } else { 'b' });
+            }
+        }
+
+        writer.push(1, '$');
+    }
+
+    writer.finish();
+    output
+}
+
+/// Writes runs of cells, merging consecutive runs of the same tag and wrapping
+/// lines at [`MAX_LINE_LENGTH`].
+struct RunWriter<'a> {
+    output: &'a mut String,
+    line_length: usize,
+
+    /// The run that hasn't been written yet, since the next run might have the
+    /// same tag.
+    pending: Option<(u32, char)>,
+}
+
+impl<'a> RunWriter<'a> {
+    fn new(output: &'a mut String) -> Self {
+        Self {
+            output,
+            line_length: 0,
+            pending: None,
+        }
+    }
+
+    fn push(&mut self, count: u32, tag: char) {
+        match &mut self.pending {
+            Some((pending_count, pending_tag)) if *pending_tag == tag => *pending_count += count,
+            _ => {
+                if let Some((count, tag)) = self.pending.take() {
+                    self.write_run(count, tag);
+                }
+                self.pending = Some((count, tag));
+            }
+        }
+    }
+
+    /// Writes the last run and the `!` ending the pattern. New lines at the end
+    /// of the pattern are implied by the `!`, so they're dropped.
+    fn finish(mut self) {
+        if let Some((count, tag)) = self.pending.take()
+            && tag != '$'
+        {
+            self.write_run(count, tag);
+        }
+
+        self.write_token("!".to_owned());
+        self.output.push('\n');
+    }
+
+    fn write_run(&mut self, count: u32, tag: char) {
+        let token = if count == 1 {
+            tag.to_string()
+        } else {
+            format!("{count}{tag}")
+        };
+        self.write_token(token);
+    }
+
+    fn write_token(&mut self, token: String) {
+        if self.line_length + token.len() > MAX_LINE_LENGTH {
+            self.output.push('\n');
+            self.line_length = 0;
+        }
+
+        self.line_length += token.len();
+        self.output.push_str(&token);
+    }
+}
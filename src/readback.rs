@@ -0,0 +1,305 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+/// A set of staging buffers that the state is copied into so it can be mapped
+/// and read on the CPU.
+///
+/// Each read uses its own staging buffer so that several reads can be in
+/// flight at once without waiting for each other. Buffers are created the
+/// first time they're needed and are reused by any later read that fits in
+/// them once the read using them finishes.
+///
+/// Buffers are never freed, so the ring grows to the largest number of reads
+/// that have been in flight at once, and finding a free buffer takes time
+/// linear in that. Callers that start many reads without waiting for them
+/// should cap how many are in flight.
+#[derive(Default)]
+pub(crate) struct StagingRing {
+    slots: Vec<Slot>,
+
+    /// The slot to start looking for a free buffer at, so that buffers are
+    /// reused in order.
+    next: usize,
+}
+
+struct Slot {
+    buffer: wgpu::Buffer,
+    state: Arc<Mutex<SlotState>>,
+}
+
+enum SlotState {
+    /// The buffer isn't being used by a read.
+    Free,
+
+    /// The buffer is being copied into and mapped, and the read will be woken
+    /// once that's done.
+    Pending(Option<Waker>),
+
+    /// Mapping the buffer finished, but the read hasn't collected the result
+    /// yet.
+    Mapped(Result<(), wgpu::BufferAsyncError>),
+
+    /// The read was dropped before mapping the buffer finished, so the buffer
+    /// is freed as soon as it is mapped.
+    Abandoned,
+
+    /// The read was dropped before it was started, while the copy into the
+    /// buffer may still be waiting in an encoder that's submitted later. The
+    /// buffer can't be reused safely, so it's removed from the ring.
+    Retired,
+}
+
+impl StagingRing {
+    /// Copies `num_blocks` blocks starting at block `offset` of `src` into a
+    /// free staging buffer and starts mapping it, returning a future that
+    /// resolves to the result of `unpack` on the copied blocks.
+    pub(crate) fn read<T>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        src: &wgpu::Buffer,
+        offset: u32,
+        num_blocks: u32,
+        unpack: impl FnOnce(&[u32]) -> T + Send + 'static,
+    ) -> StateReadback<T> {
+        self.read_with(device, queue, num_blocks, unpack, |encoder, staging| {
+            let src_offset = offset as u64 * size_of::<u32>() as u64;
+            let size = num_blocks as u64 * size_of::<u32>() as u64;
+            encoder.copy_buffer_to_buffer(src, src_offset, staging, 0, size);
+        })
+    }
+
+    /// Like [`Self::read`], except that `encode` records the commands filling
+    /// in the first `len` `u32`s of the staging buffer, e.g. a compute pass
+    /// followed by a copy of its results.
+    pub(crate) fn read_with<T>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        len: u32,
+        unpack: impl FnOnce(&[u32]) -> T + Send + 'static,
+        encode: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::Buffer),
+    ) -> StateReadback<T> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Read State Encoder"),
+        });
+        let readback = self.encode_read_with(device, &mut encoder, len, unpack, encode);
+        queue.submit([encoder.finish()]);
+        readback.submitted()
+    }
+
+    /// Like [`Self::read_with`], except that the commands are recorded into
+    /// `encoder` instead of being submitted right away.
+    pub(crate) fn encode_read_with<T>(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        len: u32,
+        unpack: impl FnOnce(&[u32]) -> T + Send + 'static,
+        encode: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::Buffer),
+    ) -> EncodedReadback<T> {
+        let size = len as u64 * size_of::<u32>() as u64;
+        let slot = self.acquire(device, size);
+        let buffer = slot.buffer.clone();
+        let state = slot.state.clone();
+        encode(encoder, &buffer);
+
+        EncodedReadback {
+            buffer,
+            state,
+            size,
+            unpack: Some(Box::new(unpack)),
+        }
+    }
+
+    /// Returns a free slot with a buffer of at least `size` bytes, marking it
+    /// as pending. Creates a new staging buffer if none of them are available.
+    fn acquire(&mut self, device: &wgpu::Device, size: u64) -> &Slot {
+        self.slots
+            .retain(|slot| !matches!(*slot.state.lock().unwrap(), SlotState::Retired));
+
+        let num_slots = self.slots.len();
+        let free = (0..num_slots)
+            .map(|offset| (self.next + offset) % num_slots)
+            .find(|&index| {
+                let slot = &self.slots[index];
+                let mut state = slot.state.lock().unwrap();
+                if slot.buffer.size() >= size && matches!(*state, SlotState::Free) {
+                    *state = SlotState::Pending(None);
+                    true
+                } else {
+                    false
+                }
+            });
+
+        let index = free.unwrap_or_else(|| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Staging Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            self.slots.push(Slot {
+                buffer,
+                state: Arc::new(Mutex::new(SlotState::Pending(None))),
+            });
+            self.slots.len() - 1
+        });
+
+        self.next = index + 1;
+        &self.slots[index]
+    }
+}
+
+type UnpackFn<T> = Box<dyn FnOnce(&[u32]) -> T + Send>;
+
+/// A read that has been recorded into a command encoder but hasn't started
+/// yet, returned by [`LifeSimulation::encode_stats`].
+///
+/// Call [`Self::submitted`] once the encoder has been submitted to start
+/// reading the results back. Dropping this instead cancels the read, and its
+/// staging buffer is never reused since the encoder may still write to it.
+///
+/// [`LifeSimulation::encode_stats`]: crate::LifeSimulation::encode_stats
+#[must_use = "the read only starts once `submitted` is called"]
+pub struct EncodedReadback<T> {
+    buffer: wgpu::Buffer,
+    state: Arc<Mutex<SlotState>>,
+    size: u64,
+
+    /// Converts the copied blocks to the output, taken when the read starts.
+    unpack: Option<UnpackFn<T>>,
+}
+
+impl<T> EncodedReadback<T> {
+    /// Starts mapping the staging buffer, returning a future that resolves to
+    /// the results.
+    ///
+    /// Must only be called after the encoder the read was recorded into has
+    /// been submitted, since buffers that are being mapped can't be used by
+    /// later submissions.
+    pub fn submitted(mut self) -> StateReadback<T> {
+        let callback_buffer = self.buffer.clone();
+        let callback_state = self.state.clone();
+        self.buffer
+            .map_async(wgpu::MapMode::Read, ..self.size, move |result| {
+                let mut state = callback_state.lock().unwrap();
+                match std::mem::replace(&mut *state, SlotState::Free) {
+                    SlotState::Pending(waker) => {
+                        *state = SlotState::Mapped(result);
+                        if let Some(waker) = waker {
+                            waker.wake();
+                        }
+                    }
+
+                    SlotState::Abandoned => {
+                        if result.is_ok() {
+                            callback_buffer.unmap();
+                        }
+                    }
+
+                    SlotState::Free | SlotState::Mapped(_) | SlotState::Retired => {
+                        unreachable!("Staging buffer was mapped without a pending read")
+                    }
+                }
+            });
+
+        StateReadback {
+            buffer: self.buffer.clone(),
+            state: self.state.clone(),
+            size: self.size,
+            unpack: self.unpack.take(),
+        }
+    }
+}
+
+impl<T> Drop for EncodedReadback<T> {
+    fn drop(&mut self) {
+        // The encoder the read was recorded into may still be submitted, and
+        // there's no telling when, so the buffer is never reused.
+        if self.unpack.is_some() {
+            *self.state.lock().unwrap() = SlotState::Retired;
+        }
+    }
+}
+
+/// A pending read of the simulation state, returned by
+/// [`LifeSimulation::read_state_async`] and
+/// [`LifeSimulation::read_region_async`], or of statistics about it, returned
+/// by [`LifeSimulation::read_stats_async`].
+///
+/// By default resolves to one byte per cell, like
+/// [`LifeSimulation::read_state`]. The read only makes progress while the
+/// device is being polled, or when work is submitted to its queue.
+///
+/// Dropping the future cancels the read and returns its staging buffer to the
+/// simulation once the GPU is done with it.
+///
+/// [`LifeSimulation::read_state_async`]: crate::LifeSimulation::read_state_async
+/// [`LifeSimulation::read_region_async`]: crate::LifeSimulation::read_region_async
+/// [`LifeSimulation::read_stats_async`]: crate::LifeSimulation::read_stats_async
+/// [`LifeSimulation::read_state`]: crate::LifeSimulation::read_state
+pub struct StateReadback<T = Vec<u8>> {
+    buffer: wgpu::Buffer,
+    state: Arc<Mutex<SlotState>>,
+
+    /// The number of bytes copied into `buffer`, which may be smaller than
+    /// the buffer itself.
+    size: u64,
+
+    /// Converts the copied blocks to the output, taken when the read
+    /// completes.
+    unpack: Option<UnpackFn<T>>,
+}
+
+impl<T> Future for StateReadback<T> {
+    type Output = Result<T, wgpu::BufferAsyncError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut state = this.state.lock().unwrap();
+        match std::mem::replace(&mut *state, SlotState::Free) {
+            SlotState::Pending(_) => {
+                *state = SlotState::Pending(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+
+            SlotState::Mapped(Ok(())) => {
+                let unpack = this.unpack.take().unwrap();
+                let view = this.buffer.get_mapped_range(..this.size);
+                let output = unpack(bytemuck::cast_slice(&view));
+                drop(view);
+                this.buffer.unmap();
+                Poll::Ready(Ok(output))
+            }
+
+            SlotState::Mapped(Err(err)) => Poll::Ready(Err(err)),
+
+            SlotState::Free | SlotState::Abandoned | SlotState::Retired => {
+                panic!("StateReadback polled after completion")
+            }
+        }
+    }
+}
+
+impl<T> Drop for StateReadback<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            SlotState::Pending(_) => *state = SlotState::Abandoned,
+
+            SlotState::Mapped(ref result) => {
+                if result.is_ok() {
+                    self.buffer.unmap();
+                }
+                *state = SlotState::Free;
+            }
+
+            SlotState::Free | SlotState::Abandoned | SlotState::Retired => {}
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{PackedGrid, Rule, Topology};
+
+/// A simple CPU implementation of the simulation, used as a reference to
+/// check the compute shaders against.
+///
+/// Each generation is computed one cell at a time by counting its neighbors,
+/// with cells beyond the edges of the grid looked up through
+/// [`Topology::resolve`]. This is far too slow for anything but testing, but
+/// is simple enough to be obviously correct.
+#[derive(Debug, Clone)]
+pub struct ReferenceSimulation {
+    grid: PackedGrid,
+    rule: Rule,
+    topology: Topology,
+    generation: u64,
+}
+
+impl ReferenceSimulation {
+    /// Creates a simulation starting from `grid`.
+    pub fn new(grid: PackedGrid, rule: Rule, topology: Topology) -> Self {
+        Self {
+            grid,
+            rule,
+            topology,
+            generation: 0,
+        }
+    }
+
+    /// Returns the current state of the grid.
+    pub fn grid(&self) -> &PackedGrid {
+        &self.grid
+    }
+
+    /// Returns the rule the simulation runs.
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Returns the topology of the grid.
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Returns the number of generations that have been simulated.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Advances the simulation by one generation.
+    pub fn step(&mut self) {
+        let [width, height] = self.grid.size();
+        let mut next = PackedGrid::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let neighbors = self.neighbors(x, y);
+                let alive = if self.grid.get(x, y) {
+                    self.rule.survives(neighbors)
+                } else {
+                    self.rule.is_born(neighbors)
+                };
+                if alive {
+                    next.set(x, y, true);
+                }
+            }
+        }
+
+        self.grid = next;
+        self.generation += 1;
+    }
+
+    /// Advances the simulation by `generations` generations.
+    pub fn steps(&mut self, generations: u64) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Counts the live neighbors of the cell at `x`, `y`.
+    fn neighbors(&self, x: u32, y: u32) -> u32 {
+        let size = self.grid.size();
+        let mut count = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let neighbor = self.topology.resolve(size, x as i64 + dx, y as i64 + dy);
+                if let Some([nx, ny]) = neighbor
+                    && self.grid.get(nx, ny)
+                {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+}
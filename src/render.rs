@@ -0,0 +1,474 @@
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use crate::LifeSimulation;
+
+/// The source of the shader used to draw the grid.
+const RENDER_SHADER_SOURCE: &str = include_str!("render.wgsl");
+
+/// The colors used to draw the grid, as sRGB colors with alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Palette {
+    /// The color of live cells. Defaults to white.
+    pub alive: [u8; 4],
+
+    /// The color of dead cells. Defaults to black.
+    pub dead: [u8; 4],
+
+    /// The color of the parts of the target outside the grid, e.g. the bars
+    /// on either side when the aspect ratio of the target doesn't match the
+    /// grid. Defaults to dark gray.
+    pub background: [u8; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            alive: [255, 255, 255, 255],
+            dead: [0, 0, 0, 255],
+            background: [32, 32, 32, 255],
+        }
+    }
+}
+
+/// Which part of the grid a [`Renderer`] draws, and how large.
+///
+/// The camera is described by the cell at the center of the target and the
+/// zoom level, so resizing the target keeps the same cells in the middle and
+/// the cells square. Positions are in cells from the top left corner of the
+/// grid, and in pixels from the top left corner of the target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The position in cells shown at the center of the target.
+    pub center: [f32; 2],
+
+    /// The width and height in cells of each pixel, so smaller values zoom
+    /// in.
+    pub cells_per_pixel: f32,
+}
+
+impl Camera {
+    /// Creates a camera that shows the `width` by `height` cells starting at
+    /// `x`, `y` as large as possible in a `target_size` target.
+    pub fn fit(x: f32, y: f32, width: f32, height: f32, target_size: [u32; 2]) -> Self {
+        let [target_width, target_height] = target_size.map(|size| size.max(1) as f32);
+        Self {
+            center: [x + width / 2.0, y + height / 2.0],
+            cells_per_pixel: (width / target_width).max(height / target_height),
+        }
+    }
+
+    /// Creates a camera that shows the whole grid of `sim` as large as
+    /// possible in a `target_size` target.
+    pub fn fit_grid(sim: &LifeSimulation, target_size: [u32; 2]) -> Self {
+        let [width, height] = sim.logical_grid_size.map(|size| size as f32);
+        Self::fit(0.0, 0.0, width, height, target_size)
+    }
+
+    /// The position in cells of the top left corner of a `target_size`
+    /// target.
+    pub fn origin(&self, target_size: [u32; 2]) -> [f32; 2] {
+        let [target_width, target_height] = target_size.map(|size| size as f32);
+        [
+            self.center[0] - target_width * self.cells_per_pixel / 2.0,
+            self.center[1] - target_height * self.cells_per_pixel / 2.0,
+        ]
+    }
+
+    /// Returns the position in cells under the pixel position `pixel` of a
+    /// `target_size` target.
+    pub fn cell_at(&self, pixel: [f32; 2], target_size: [u32; 2]) -> [f32; 2] {
+        let [x, y] = self.origin(target_size);
+        [
+            x + pixel[0] * self.cells_per_pixel,
+            y + pixel[1] * self.cells_per_pixel,
+        ]
+    }
+
+    /// Moves the view by `delta` pixels, so that the cells under the cursor
+    /// follow it when dragging.
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        self.center[0] -= delta[0] * self.cells_per_pixel;
+        self.center[1] -= delta[1] * self.cells_per_pixel;
+    }
+
+    /// Zooms in by `factor`, or out if it's less than 1, keeping the cell
+    /// under the pixel position `pixel` of a `target_size` target in place.
+    pub fn zoom_at(&mut self, factor: f32, pixel: [f32; 2], target_size: [u32; 2]) {
+        let before = self.cell_at(pixel, target_size);
+        self.cells_per_pixel /= factor;
+        let after = self.cell_at(pixel, target_size);
+        self.center[0] += before[0] - after[0];
+        self.center[1] += before[1] - after[1];
+    }
+}
+
+/// The uniforms used by the render shader. Must match `RenderParams` in
+/// render.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct RenderParams {
+    grid_size: [u32; 2],
+    blocks_per_row: u32,
+    _padding: u32,
+    origin: [f32; 2],
+    cells_per_pixel: [f32; 2],
+    alive: [f32; 4],
+    dead: [f32; 4],
+    background: [f32; 4],
+}
+
+/// Draws the state of a [`LifeSimulation`] into a texture, without needing a
+/// window.
+///
+/// Each renderer is tied to the state buffers of the simulation it was
+/// created for. By default it draws the whole grid scaled to fit the target,
+/// keeping the cells square, and a [`Camera`] can be set to draw part of it.
+#[derive(Debug)]
+pub struct Renderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    params_buf: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+
+    /// The colors used by the next call to [`Self::encode`].
+    pub palette: Palette,
+
+    /// The part of the grid drawn by the next call to [`Self::encode`], or
+    /// `None` to fit the whole grid to the target.
+    pub camera: Option<Camera>,
+}
+
+impl Renderer {
+    /// The texture format used by [`Self::render_image`].
+    pub const IMAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Creates a renderer for `sim` that draws into textures of `format`.
+    pub fn new(sim: &LifeSimulation, format: wgpu::TextureFormat) -> Self {
+        let device = &sim.device;
+
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Render Params Buffer"),
+            size: size_of::<RenderParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Render Bind Group Layout"),
+            entries: &[
+                // state
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // params
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let create_bind_group = |label, state_buf: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: state_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: params_buf.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [
+            create_bind_group("Render Bind Group A", &sim.state_bufs[0]),
+            create_bind_group("Render Bind Group B", &sim.state_bufs[1]),
+        ];
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Render Shader"),
+            source: wgpu::ShaderSource::Wgsl(RENDER_SHADER_SOURCE.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("render_vertex"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("render_fragment"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_groups,
+            params_buf,
+            format,
+            palette: Palette::default(),
+            camera: None,
+        }
+    }
+
+    /// Creates a renderer for `sim` that can be used with
+    /// [`Self::render_image`].
+    pub fn offscreen(sim: &LifeSimulation) -> Self {
+        Self::new(sim, Self::IMAGE_FORMAT)
+    }
+
+    /// The format of the textures this renderer draws into.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Encodes a render pass drawing the current generation of `sim` into
+    /// `view`, which is `target_size` pixels large.
+    ///
+    /// The colors and position of the grid are written to a uniform buffer
+    /// when this is called, so encoding several passes with different
+    /// settings before submitting them isn't supported.
+    pub fn encode(
+        &self,
+        sim: &LifeSimulation,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        target_size: [u32; 2],
+    ) {
+        let params = self.params(sim, target_size);
+        sim.queue
+            .write_buffer(&self.params_buf, 0, bytemuck::bytes_of(&params));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_groups[sim.current_state], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Draws the current generation of `sim` into a `width` by `height` image
+    /// and reads it back, blocking until the GPU is done.
+    ///
+    /// Panics if the renderer wasn't created with [`Self::IMAGE_FORMAT`], e.g.
+    /// with [`Self::offscreen`].
+    pub fn render_image(&self, sim: &LifeSimulation, width: u32, height: u32) -> Image {
+        assert_eq!(
+            self.format,
+            Self::IMAGE_FORMAT,
+            "Only renderers for {:?} can render images",
+            Self::IMAGE_FORMAT,
+        );
+
+        let device = &sim.device;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::IMAGE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Rows copied out of a texture have to be aligned, so the padding is
+        // stripped after reading the image back.
+        let row_len = width * 4;
+        let padded_row_len = row_len.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Image Staging Buffer"),
+            size: padded_row_len as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Image Encoder"),
+        });
+        self.encode(sim, &mut encoder, &view, [width, height]);
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_len),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        sim.queue.submit([encoder.finish()]);
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map image buffer");
+        });
+        device
+            .poll(wgpu::PollType::Wait)
+            .expect("Failed to poll device");
+
+        let data = slice.get_mapped_range();
+        let pixels = data
+            .chunks(padded_row_len as usize)
+            .flat_map(|row| &row[..row_len as usize])
+            .copied()
+            .collect();
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Computes the uniforms for drawing `sim` into a `target_size` target,
+    /// as seen by the camera.
+    fn params(&self, sim: &LifeSimulation, target_size: [u32; 2]) -> RenderParams {
+        let camera = self
+            .camera
+            .unwrap_or_else(|| Camera::fit_grid(sim, target_size));
+        let scale = camera.cells_per_pixel;
+        let origin = camera.origin(target_size);
+
+        // The shader outputs linear colors, which are converted back to sRGB
+        // when writing to sRGB targets.
+        let srgb = self.format.is_srgb();
+        let color = |color: [u8; 4]| {
+            let [r, g, b, a] = color.map(|channel| channel as f32 / 255.0);
+            if srgb {
+                [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+            } else {
+                [r, g, b, a]
+            }
+        };
+
+        RenderParams {
+            grid_size: sim.logical_grid_size,
+            blocks_per_row: sim.physical_grid_size[0],
+            _padding: 0,
+            origin,
+            cells_per_pixel: [scale, scale],
+            alive: color(self.palette.alive),
+            dead: color(self.palette.dead),
+            background: color(self.palette.background),
+        }
+    }
+}
+
+/// Converts a color channel from sRGB to linear.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// An image drawn by [`Renderer::render_image`], as rows of sRGB pixels with
+/// alpha.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the pixels in row-major order, with four bytes per pixel.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the color of the pixel at `x`, `y`.
+    ///
+    /// Panics if the pixel is outside of the image.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.width && y < self.height, "Pixel out of bounds");
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[index..index + 4].try_into().unwrap()
+    }
+
+    /// Encodes the image as a PNG.
+    pub fn write_png(&self, writer: impl io::Write) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Saves the image as a PNG file at `path`.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_png(BufWriter::new(file))
+    }
+}
@@ -0,0 +1,52 @@
+// Draws the simulation state into a texture, used by `Renderer`.
+//
+// Instead of drawing a quad per cell, a single triangle covers the whole
+// target and each fragment looks up the cell under it. This costs the same no
+// matter how many cells there are, and scales the cells with nearest-neighbor
+// sampling.
+
+@group(0) @binding(0) var<storage> state: array<u32>;
+
+// Must match `RenderParams` in render.rs.
+struct RenderParams {
+    grid_size: vec2u,
+    blocks_per_row: u32,
+
+    // The position in cells of the top left corner of the target, and the
+    // size in cells of each pixel.
+    origin: vec2f,
+    cells_per_pixel: vec2f,
+
+    // Linear colors for live cells, dead cells and anything outside the grid.
+    alive: vec4f,
+    dead: vec4f,
+    background: vec4f,
+}
+
+@group(0) @binding(1) var<uniform> params: RenderParams;
+
+@vertex
+fn render_vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    // A triangle with corners at (-1, -1), (3, -1) and (-1, 3), which covers
+    // all of clip space.
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn render_fragment(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    // `position` is at the center of the pixel, in pixels from the top left
+    // corner of the target.
+    let cell = floor(params.origin + position.xy * params.cells_per_pixel);
+    if any(cell < vec2f(0.0)) || any(cell >= vec2f(params.grid_size)) {
+        return params.background;
+    }
+
+    let x = u32(cell.x);
+    let y = u32(cell.y);
+    let block = state[y * params.blocks_per_row + x / 32u];
+    if ((block >> (x % 32u)) & 1u) != 0u {
+        return params.alive;
+    }
+    return params.dead;
+}
@@ -0,0 +1,161 @@
+use std::{fmt, str::FromStr};
+
+/// An outer-totalistic rule on the Moore neighborhood, i.e. a rule where the
+/// next state of a cell only depends on its current state and the number of
+/// live cells among its 8 neighbors.
+///
+/// Rules are usually written in B/S notation, e.g. `B3/S23` for Conway's game
+/// of life or `B36/S23` for HighLife, and can be parsed from a string with
+/// [`str::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// Bit `n` is set if a dead cell with `n` live neighbors becomes alive.
+    pub birth: u16,
+
+    /// Bit `n` is set if a live cell with `n` live neighbors stays alive.
+    pub survival: u16,
+}
+
+impl Rule {
+    /// Conway's game of life, `B3/S23`.
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: (1 << 2) | (1 << 3),
+    };
+
+    /// Creates a rule from the lists of neighbor counts that cause a birth and
+    /// that let a cell survive.
+    ///
+    /// Panics if any of the counts are greater than 8.
+    pub fn new(birth: &[u8], survival: &[u8]) -> Self {
+        fn to_mask(counts: &[u8]) -> u16 {
+            counts.iter().fold(0, |mask, &count| {
+                assert!(count <= 8, "Neighbor count {count} is out of range");
+                mask | (1 << count)
+            })
+        }
+
+        Self {
+            birth: to_mask(birth),
+            survival: to_mask(survival),
+        }
+    }
+
+    /// Returns `true` if a dead cell with `neighbors` live neighbors is born.
+    pub fn is_born(&self, neighbors: u32) -> bool {
+        neighbors <= 8 && (self.birth >> neighbors) & 1 != 0
+    }
+
+    /// Returns `true` if a live cell with `neighbors` live neighbors survives.
+    pub fn survives(&self, neighbors: u32) -> bool {
+        neighbors <= 8 && (self.survival >> neighbors) & 1 != 0
+    }
+
+    /// Returns the birth and survival masks in the layout expected by the
+    /// `rule` uniform in the compute shader.
+    pub fn to_masks(&self) -> [u32; 2] {
+        [self.birth as u32, self.survival as u32]
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("B")?;
+        for count in 0..=8 {
+            if self.is_born(count) {
+                write!(f, "{count}")?;
+            }
+        }
+
+        f.write_str("/S")?;
+        for count in 0..=8 {
+            if self.survives(count) {
+                write!(f, "{count}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    /// Parses a rulestring.
+    ///
+    /// Both the standard `B3/S23` notation (in either order and either case)
+    /// and the older `23/3` survival/birth notation are accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (first, second) = s.split_once('/').ok_or(ParseRuleError::MissingSeparator)?;
+
+        let first_prefix = first.chars().next().map(|c| c.to_ascii_uppercase());
+        let second_prefix = second.chars().next().map(|c| c.to_ascii_uppercase());
+
+        let (birth, survival) = match (first_prefix, second_prefix) {
+            (Some('B'), Some('S')) => (&first[1..], &second[1..]),
+            (Some('S'), Some('B')) => (&second[1..], &first[1..]),
+
+            // Without prefixes the rule is in the older S/B notation, e.g.
+            // "23/3" for Conway's game of life.
+            _ if !first.starts_with(char::is_alphabetic)
+                && !second.starts_with(char::is_alphabetic) =>
+            {
+                (second, first)
+            }
+
+            _ => return Err(ParseRuleError::InvalidPrefix),
+        };
+
+        Ok(Rule {
+            birth: parse_counts(birth)?,
+            survival: parse_counts(survival)?,
+        })
+    }
+}
+
+/// Parses a list of neighbor counts, e.g. the "23" in "S23", into a mask.
+fn parse_counts(counts: &str) -> Result<u16, ParseRuleError> {
+    counts.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+        Some(count) if count <= 8 => Ok(mask | (1 << count)),
+        Some(_) => Err(ParseRuleError::CountOutOfRange(c)),
+        None => Err(ParseRuleError::InvalidCharacter(c)),
+    })
+}
+
+/// The error returned when parsing a [`Rule`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRuleError {
+    /// The rule doesn't have the `/` separating birth and survival counts.
+    MissingSeparator,
+
+    /// The two halves of the rule aren't marked with `B` and `S`.
+    InvalidPrefix,
+
+    /// A neighbor count was greater than 8.
+    CountOutOfRange(char),
+
+    /// A character other than a digit appeared in a list of neighbor counts.
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "rule is missing the '/' separator"),
+            Self::InvalidPrefix => write!(f, "rule halves must be prefixed with 'B' and 'S'"),
+            Self::CountOutOfRange(c) => {
+                write!(f, "neighbor count {c} is out of range, must be 0-8")
+            }
+            Self::InvalidCharacter(c) => write!(f, "unexpected character {c:?} in rule"),
+        }
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
@@ -1,13 +1,47 @@
-@group(0) @binding(0) var<uniform> grid_sizef: vec2f;
 @group(0) @binding(4) var<uniform> grid_sizeu: vec2u;
 @group(0) @binding(3) var<uniform> physical_grid_size: vec2u;
 @group(0) @binding(1) var<storage> in_state: array<u32>;
 @group(0) @binding(2) var<storage, read_write> out_state: array<u32>;
 
+// The birth and survival masks for the current rule. Bit `n` of `rule.x` is set
+// if a dead cell with `n` neighbors is born, and bit `n` of `rule.y` is set if a
+// live cell with `n` neighbors survives.
+@group(0) @binding(5) var<uniform> rule: vec2u;
+
+// Describes how the edges of the grid are joined. See `Topology::to_uniform`.
+struct Topology {
+    // 1 if the edges are joined, 0 if cells outside the grid are always dead.
+    joined: u32,
+
+    // Bit 0 is set if the top and bottom edges are twisted, bit 1 is set if the
+    // left and right edges are twisted.
+    twisted: u32,
+
+    // Shift applied along twisted edges after mirroring.
+    shift: u32,
+}
+
+@group(0) @binding(6) var<uniform> topology: Topology;
+
+const TWIST_HORIZONTAL: u32 = 1u;
+const TWIST_VERTICAL: u32 = 2u;
+
+// Masks for the bits of a block that are needed from the blocks to the left
+// and right of the block being updated: only the last cell of the block to the
+// left and the first cell of the block to the right are neighbors.
+const WEST_EDGE: u32 = 0x80000000u;
+const EAST_EDGE: u32 = 0x00000001u;
+const ALL_CELLS: u32 = 0xffffffffu;
+
 // TODO: Inject the workgroup size at runtime?
 @compute @workgroup_size(64)
-fn compute_main(@builtin(global_invocation_id) invocation: vec3u) {
-    let block_index = invocation.x;
+fn compute_main(
+    @builtin(global_invocation_id) invocation: vec3u,
+    @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+    // Large grids are dispatched as several rows of workgroups, since there's
+    // a limit on the number of workgroups in each dimension.
+    let block_index = invocation.y * num_workgroups.x * 64 + invocation.x;
 
     // If the number of blocks isn't a clean multiple of the workgroup size we
     // end up with extra invocations that don't correspond to a real block. We
@@ -22,126 +56,419 @@ fn compute_main(@builtin(global_invocation_id) invocation: vec3u) {
         return;
     }
 
-    // Calculate the cell index that this block starts at. This is used to
-    // calculate the index for each cell in the block.
-    let block_row = block_index / physical_grid_size.x;
-    let block_col = block_index % physical_grid_size.x;
-    let block_start_cell = block_row * grid_sizeu.x + block_col * 32;
-
-    let block_in = in_state[block_index];
-    var block_out = 0u;
-
-    // Calculate the maximum bit for this block in case the current row ends in
-    // the middle of the block. This comes up e.g. in the tests when using an
-    // 8x8 grid.
-    let max_bit = min(32u, grid_sizeu.x - block_col * 32);
-
-    for (var bit_index = 0u; bit_index < max_bit; bit_index++) {
-        let cell_index = block_start_cell + bit_index;
-        let cell = cell_index_to_cell_coords(cell_index);
-
-        // Determine how many active neighbors this cell has.
-        let active_neighbors =
-            cell_active(cell.x + 1, cell.y + 1) +
-            cell_active(cell.x + 1, cell.y    ) +
-            cell_active(cell.x + 1, cell.y - 1) +
-            cell_active(cell.x,     cell.y - 1) +
-            cell_active(cell.x - 1, cell.y - 1) +
-            cell_active(cell.x - 1, cell.y    ) +
-            cell_active(cell.x - 1, cell.y + 1) +
-            cell_active(cell.x,     cell.y + 1);
-
-        let mask = 1u << bit_index;
-
-        // Conway's game of life rules:
-        switch active_neighbors {
-            // Active cells with 2 neighbors stay active.
-            case 2: {
-                block_out |= block_in & mask;
-            }
+    let col = i32(block_index % physical_grid_size.x);
+    let row = i32(block_index / physical_grid_size.x);
 
-            // Cells with 3 neighbors become or stay active.
-            case 3: {
-                block_out |= mask;
-            }
+    // Load the block and its 8 neighboring blocks once, then compute the next
+    // state of all 32 cells at the same time.
+    let next = step_block(
+        load_block(col - 1, row - 1, WEST_EDGE),
+        load_block(col,     row - 1, ALL_CELLS),
+        load_block(col + 1, row - 1, EAST_EDGE),
+        load_block(col - 1, row,     WEST_EDGE),
+        load_block(col,     row,     ALL_CELLS),
+        load_block(col + 1, row,     EAST_EDGE),
+        load_block(col - 1, row + 1, WEST_EDGE),
+        load_block(col,     row + 1, ALL_CELLS),
+        load_block(col + 1, row + 1, EAST_EDGE),
+    );
+
+    out_state[block_index] = next & valid_bits(col);
+}
+
+// The size in blocks of the tile processed by each workgroup in
+// `compute_tiled`. Must match `TILE_SIZE` in lib.rs.
+const TILE_WIDTH: u32 = 8u;
+const TILE_HEIGHT: u32 = 8u;
+
+// The tile is stored with a one block halo on every side.
+const TILE_STRIDE: u32 = TILE_WIDTH + 2u;
+const TILE_BLOCKS: u32 = TILE_STRIDE * (TILE_HEIGHT + 2u);
+
+var<workgroup> tile: array<u32, TILE_BLOCKS>;
 
-            // Cells with < 2 or > 3 neighbors become inactive.
-            default: {
-                block_out &= ~mask;
+// Same as `compute_main`, except that each workgroup first loads a tile of
+// blocks into workgroup memory so that neighboring invocations don't each load
+// the same blocks from `in_state`.
+@compute @workgroup_size(TILE_WIDTH, TILE_HEIGHT)
+fn compute_tiled(
+    @builtin(workgroup_id) workgroup: vec3u,
+    @builtin(local_invocation_id) local: vec3u,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    // The block coordinates of the top left corner of the halo.
+    let origin = vec2i(workgroup.xy * vec2u(TILE_WIDTH, TILE_HEIGHT)) - 1;
+
+    // Cooperatively load the tile and its halo. There are more blocks in the
+    // tile than invocations in the workgroup, so some invocations load more
+    // than one block.
+    for (var i = local_index; i < TILE_BLOCKS; i += TILE_WIDTH * TILE_HEIGHT) {
+        let tile_col = i % TILE_STRIDE;
+        let tile_row = i / TILE_STRIDE;
+        let col = origin.x + i32(tile_col);
+        let row = origin.y + i32(tile_row);
+
+        // Tiles along the right and bottom edges of the grid can extend past
+        // the grid by more than the halo, and those blocks are never used.
+        if col > i32(physical_grid_size.x) || row > i32(physical_grid_size.y) {
+            tile[i] = 0u;
+            continue;
+        }
+
+        var mask = ALL_CELLS;
+        if tile_col == 0u {
+            mask = WEST_EDGE;
+        } else if tile_col == TILE_STRIDE - 1u {
+            mask = EAST_EDGE;
+        }
+
+        tile[i] = load_block(col, row, mask);
+    }
+
+    workgroupBarrier();
+
+    let col = origin.x + 1 + i32(local.x);
+    let row = origin.y + 1 + i32(local.y);
+    if u32(col) >= physical_grid_size.x || u32(row) >= physical_grid_size.y {
+        return;
+    }
+
+    let i = (local.y + 1u) * TILE_STRIDE + local.x + 1u;
+    let next = step_block(
+        tile[i - TILE_STRIDE - 1u], tile[i - TILE_STRIDE], tile[i - TILE_STRIDE + 1u],
+        tile[i - 1u],               tile[i],               tile[i + 1u],
+        tile[i + TILE_STRIDE - 1u], tile[i + TILE_STRIDE], tile[i + TILE_STRIDE + 1u],
+    );
+
+    out_state[u32(row) * physical_grid_size.x + u32(col)] = next & valid_bits(col);
+}
+
+// The number of generations computed by each dispatch of `compute_temporal`.
+// Set when creating the pipeline, and must be at most `MAX_GENERATIONS`.
+override GENERATIONS: u32 = 1u;
+
+// Must match `Kernel::MAX_GENERATIONS` in config.rs.
+const MAX_GENERATIONS: u32 = 16u;
+
+// The number of rows written by each workgroup in `compute_temporal`. Must
+// match `TEMPORAL_TILE_SIZE` in lib.rs.
+const TEMPORAL_HEIGHT: u32 = 32u;
+
+// The tile has a one block halo on the left and right, which covers up to 32
+// cells, and a halo of `GENERATIONS` rows above and below.
+const TEMPORAL_ROWS: u32 = TEMPORAL_HEIGHT + 2u * MAX_GENERATIONS;
+const TEMPORAL_BLOCKS: u32 = TILE_STRIDE * TEMPORAL_ROWS;
+
+// Two copies of the tile, so that each generation can read from one and write
+// to the other.
+var<workgroup> temporal_tiles: array<array<u32, TEMPORAL_BLOCKS>, 2>;
+
+// Same as `compute_tiled`, except that each workgroup advances its tile by
+// `GENERATIONS` generations before writing it back to `out_state`.
+//
+// Information travels at most one cell per generation, so as long as the halo
+// is at least `GENERATIONS` cells wide the cells in the middle of the tile are
+// never affected by the blocks that are missing outside of the halo.
+@compute @workgroup_size(TILE_WIDTH, TILE_HEIGHT)
+fn compute_temporal(
+    @builtin(workgroup_id) workgroup: vec3u,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let rows = TEMPORAL_HEIGHT + 2u * GENERATIONS;
+    let num_blocks = TILE_STRIDE * rows;
+
+    // The block coordinates of the top left corner of the halo.
+    let origin =
+        vec2i(workgroup.xy * vec2u(TILE_WIDTH, TEMPORAL_HEIGHT)) -
+        vec2i(1, i32(GENERATIONS));
+
+    for (var i = local_index; i < num_blocks; i += TILE_WIDTH * TILE_HEIGHT) {
+        let col = origin.x + i32(i % TILE_STRIDE);
+        let row = origin.y + i32(i / TILE_STRIDE);
+
+        // Skip blocks that are further past the right or bottom edges of the
+        // grid than the halo needs.
+        if col > i32(physical_grid_size.x) ||
+            row >= i32(physical_grid_size.y + GENERATIONS) {
+            temporal_tiles[0][i] = 0u;
+            continue;
+        }
+
+        temporal_tiles[0][i] = load_block(col, row, ALL_CELLS);
+    }
+
+    workgroupBarrier();
+
+    for (var generation = 0u; generation < GENERATIONS; generation++) {
+        let src = generation % 2u;
+        let dst = 1u - src;
+
+        for (var i = local_index; i < num_blocks; i += TILE_WIDTH * TILE_HEIGHT) {
+            let tile_col = i32(i % TILE_STRIDE);
+            let tile_row = i32(i / TILE_STRIDE);
+
+            var next = step_block(
+                temporal_block(src, tile_col - 1, tile_row - 1, rows),
+                temporal_block(src, tile_col,     tile_row - 1, rows),
+                temporal_block(src, tile_col + 1, tile_row - 1, rows),
+                temporal_block(src, tile_col - 1, tile_row,     rows),
+                temporal_block(src, tile_col,     tile_row,     rows),
+                temporal_block(src, tile_col + 1, tile_row,     rows),
+                temporal_block(src, tile_col - 1, tile_row + 1, rows),
+                temporal_block(src, tile_col,     tile_row + 1, rows),
+                temporal_block(src, tile_col + 1, tile_row + 1, rows),
+            );
+
+            // Cells outside of the grid are copies of cells inside the grid
+            // when the edges are joined, so they evolve along with the rest of
+            // the tile. Otherwise they have to stay dead.
+            if topology.joined == 0u {
+                next &= grid_cells(origin.x + tile_col, origin.y + tile_row);
             }
+
+            temporal_tiles[dst][i] = next;
+        }
+
+        workgroupBarrier();
+    }
+
+    let result = GENERATIONS % 2u;
+    for (var i = local_index; i < TILE_WIDTH * TEMPORAL_HEIGHT; i += TILE_WIDTH * TILE_HEIGHT) {
+        let tile_col = i % TILE_WIDTH + 1u;
+        let tile_row = i / TILE_WIDTH + GENERATIONS;
+        let col = origin.x + i32(tile_col);
+        let row = origin.y + i32(tile_row);
+        if u32(col) >= physical_grid_size.x || u32(row) >= physical_grid_size.y {
+            continue;
+        }
+
+        let block = temporal_tiles[result][tile_row * TILE_STRIDE + tile_col];
+        out_state[u32(row) * physical_grid_size.x + u32(col)] = block & valid_bits(col);
+    }
+}
+
+// Reads a block from one of the copies of the tile in `compute_temporal`.
+// Blocks outside of the tile are treated as dead, which only affects the cells
+// in the halo.
+fn temporal_block(tile: u32, tile_col: i32, tile_row: i32, rows: u32) -> u32 {
+    if tile_col < 0 || tile_col >= i32(TILE_STRIDE) ||
+        tile_row < 0 || tile_row >= i32(rows) {
+        return 0u;
+    }
+
+    return temporal_tiles[tile][u32(tile_row) * TILE_STRIDE + u32(tile_col)];
+}
+
+// Returns a mask of the bits in the block at `(col, row)` that correspond to
+// cells within the grid, which is 0 for blocks outside of the grid.
+fn grid_cells(col: i32, row: i32) -> u32 {
+    if col < 0 || u32(col) >= physical_grid_size.x ||
+        row < 0 || u32(row) >= physical_grid_size.y {
+        return 0u;
+    }
+
+    return valid_bits(col);
+}
+
+// Returns a mask of the bits in the block at column `col` that correspond to
+// cells within the grid. This will be all bits except for the last block in a
+// row when the row ends in the middle of the block. This comes up e.g. in the
+// tests when using an 8x8 grid.
+fn valid_bits(col: i32) -> u32 {
+    let num_bits = min(32u, grid_sizeu.x - u32(col) * 32u);
+    return select(ALL_CELLS, (1u << num_bits) - 1u, num_bits < 32u);
+}
+
+// Loads the 32 cells starting at cell `(col * 32, row)` as a block.
+//
+// `col` and `row` may refer to a block that is partially or entirely outside of
+// the grid, in which case the cells outside of the grid are resolved according
+// to the topology. Only the bits set in `mask` are guaranteed to be loaded,
+// which lets us avoid resolving cells that won't be used.
+fn load_block(col: i32, row: i32, mask: u32) -> u32 {
+    let x = col * 32;
+
+    var y = row;
+    if row < 0 || row >= i32(grid_sizeu.y) {
+        if topology.joined == 0u {
+            return 0u;
+        }
+
+        // Crossing a twisted edge mirrors the row, so the cells have to be
+        // looked up individually.
+        if (topology.twisted & TWIST_HORIZONTAL) != 0u {
+            return gather_block(x, row, mask);
         }
+
+        y = i32(wrap(row, grid_sizeu.y));
+    }
+
+    if col < 0 || u32(col) >= physical_grid_size.x {
+        return gather_block(x, y, mask);
+    }
+
+    // Fast path: The block is within the grid, so we can load it directly. If
+    // the row ends in the middle of the block we still need to look up the
+    // cells past the end of the row.
+    let block = in_state[u32(y) * physical_grid_size.x + u32(col)];
+    let valid = valid_bits(col);
+    if (mask & ~valid) == 0u {
+        return block;
+    }
+
+    return (block & valid) | gather_block(x, y, mask & ~valid);
+}
+
+// Builds a block by looking up each of the cells selected by `mask`
+// individually, starting with the cell at `(x, y)`.
+fn gather_block(x: i32, y: i32, mask: u32) -> u32 {
+    var block = 0u;
+    for (var bit = 0u; bit < 32u; bit++) {
+        if ((mask >> bit) & 1u) != 0u {
+            block |= cell_active(x + i32(bit), y) << bit;
+        }
+    }
+
+    return block;
+}
+
+// Computes the next state of all 32 cells in block `c` given the blocks around
+// it, using bitwise operations to process every cell in parallel.
+fn step_block(
+    nw: u32, n: u32, ne: u32,
+    w: u32,  c: u32, e: u32,
+    sw: u32, s: u32, se: u32,
+) -> u32 {
+    // Shift the neighboring blocks so that each cell's neighbors line up with
+    // the cell. Bit `i` of a block is the cell at `x + i`, so shifting left by
+    // one moves each cell's west neighbor into its position, with the last cell
+    // of the block to the left filling in bit 0.
+    let n_west = (n << 1u) | (nw >> 31u);
+    let n_east = (n >> 1u) | (ne << 31u);
+    let c_west = (c << 1u) | (w >> 31u);
+    let c_east = (c >> 1u) | (e << 31u);
+    let s_west = (s << 1u) | (sw >> 31u);
+    let s_east = (s >> 1u) | (se << 31u);
+
+    // Sum up the 8 neighbors using a tree of adders, producing a 4 bit count
+    // for each cell split across 4 bit planes.
+    let north = full_add(n_west, n, n_east);
+    let middle = full_add(c_west, c_east, s_west);
+    let south = half_add(s, s_east);
+
+    let ones = full_add(north.x, middle.x, south.x);
+    let twos = full_add(north.y, middle.y, south.y);
+    let twos_carry = half_add(twos.x, ones.y);
+    let fours = half_add(twos.y, twos_carry.y);
+
+    let count0 = ones.x;
+    let count1 = twos_carry.x;
+    let count2 = fours.x;
+    let count3 = fours.y;
+
+    // Apply the rule. For each possible neighbor count, find the cells with
+    // that many neighbors and use the birth or survival mask to determine if
+    // they'll be alive in the next generation.
+    var next = 0u;
+    for (var count = 0u; count <= 8u; count++) {
+        let matches =
+            select(~count0, count0, (count & 1u) != 0u) &
+            select(~count1, count1, (count & 2u) != 0u) &
+            select(~count2, count2, (count & 4u) != 0u) &
+            select(~count3, count3, (count & 8u) != 0u);
+
+        let born = select(0u, ~c, ((rule.x >> count) & 1u) != 0u);
+        let survives = select(0u, c, ((rule.y >> count) & 1u) != 0u);
+        next |= matches & (born | survives);
     }
 
-    out_state[block_index] = block_out;
+    return next;
+}
+
+// Adds three bit planes, returning the sum in `x` and the carry in `y`.
+fn full_add(a: u32, b: u32, c: u32) -> vec2u {
+    let partial = a ^ b;
+    return vec2u(partial ^ c, (a & b) | (partial & c));
+}
+
+// Adds two bit planes, returning the sum in `x` and the carry in `y`.
+fn half_add(a: u32, b: u32) -> vec2u {
+    return vec2u(a ^ b, a & b);
 }
 
 // Converts the cell coordinates into block coordinates (i.e. block index and
-// bit index).
+// bit index). The coordinates must already be within the grid.
 //
 // TODO: Create a struct for the block coords to make the block index vs bit
 // index more clear.
 fn block_index(cell: vec2u) -> vec2u {
-    let wrapped_coords = vec2u(
-        cell.x % grid_sizeu.x,
-        cell.y % grid_sizeu.y,
-    );
-
-    let block_index =
-        physical_grid_size.x * wrapped_coords.y + wrapped_coords.x / 32;
+    let block_index = physical_grid_size.x * cell.y + cell.x / 32;
     let bit_index = cell.x % 32;
     return vec2u(block_index, bit_index);
 }
 
-fn cell_active(x: u32, y: u32) -> u32 {
-    let block_coords = block_index(vec2u(x, y));
-    let block_index = block_coords.x;
-    let bit_index = block_coords.y;
-
-    let mask = 1u << bit_index;
-    return u32((in_state[block_index] & mask) != 0u);
-}
+// Wraps a coordinate into the range `0..size`.
+//
+// Cell coordinates may be negative when looking up neighbors, and `%` on
+// negative integers isn't portable across backends (GLSL leaves it undefined),
+// so negative values are handled separately.
+fn wrap(value: i32, size: u32) -> u32 {
+    if value >= 0 {
+        return u32(value) % size;
+    }
 
-fn cell_index_to_cell_coords(index: u32) -> vec2u {
-    return vec2u(index % grid_sizeu.x, index / grid_sizeu.x);
+    return size - 1u - u32(-(value + 1)) % size;
 }
 
-// =============================================================================
-// Rendering
-// =============================================================================
-
-@vertex
-fn vertex_main(
-    @location(0) pos: vec2f,
-    @builtin(instance_index) cell_index: u32,
-) -> @builtin(position) vec4f {
-    // Calculate the coordinates of the cell in the grid.
-    let cell_coords = cell_index_to_cell_coords(cell_index);
-
-    // Calculate the size of a cell in clip space.
-    let cell_size = 2.0 / grid_sizef;
+// Divides a coordinate by `size`, rounding towards negative infinity.
+fn floor_div(value: i32, size: u32) -> i32 {
+    if value >= 0 {
+        return i32(u32(value) / size);
+    }
 
-    // Divide by the grid size so that our square is the correct size.
-    var grid_pos = pos / grid_sizef;
+    return -1 - i32(u32(-(value + 1)) / size);
+}
 
-    // Shift the square so that its top left corner is centered in the window.
-    grid_pos += (cell_size / 2f) * vec2f(1, -1);
+// Returns 1 if the cell at the given coordinates is alive, and 0 otherwise.
+//
+// Coordinates outside of the grid are mapped back onto the grid according to
+// the topology. This needs to match `Topology::resolve`.
+fn cell_active(x: i32, y: i32) -> u32 {
+    let grid_size = vec2i(grid_sizeu);
+    let outside_x = x < 0 || x >= grid_size.x;
+    let outside_y = y < 0 || y >= grid_size.y;
+    if (outside_x || outside_y) && topology.joined == 0u {
+        return 0u;
+    }
 
-    // Shift the square so that its top left corner is in the top left corner of the window.
-    grid_pos += vec2f(-1, 1);
+    var cell = vec2i(x, y);
 
-    // Shift the square to the position for its cell coordinates.
-    grid_pos += vec2f(cell_coords) * cell_size * vec2f(1, -1);
+    // Cross the left or right edges first. Coordinates far enough outside the
+    // grid cross the edges several times, and crossing a twisted edge twice
+    // cancels out the mirroring.
+    //
+    // Mirroring `y` may move it off the grid, in which case it gets wrapped
+    // when crossing the top or bottom edges.
+    if outside_x {
+        let crossings = floor_div(cell.x, grid_sizeu.x);
+        cell.x = i32(wrap(cell.x, grid_sizeu.x));
+        if (topology.twisted & TWIST_VERTICAL) != 0u && (crossings & 1) != 0 {
+            cell.y = grid_size.y - 1 - cell.y + i32(topology.shift);
+        }
+    }
 
-    // Scale the square to 0 if the cell is disabled.
-    grid_pos *= f32(cell_active(
-        cell_coords.x,
-        cell_coords.y,
-    ));
+    if cell.y < 0 || cell.y >= grid_size.y {
+        let crossings = floor_div(cell.y, grid_sizeu.y);
+        cell.y = i32(wrap(cell.y, grid_sizeu.y));
+        if (topology.twisted & TWIST_HORIZONTAL) != 0u && (crossings & 1) != 0 {
+            cell.x = i32(wrap(grid_size.x - 1 - cell.x + i32(topology.shift), grid_sizeu.x));
+        }
+    }
 
-    return vec4f(grid_pos, 0, 1);
-}
+    let block_coords = block_index(vec2u(cell));
+    let block_index = block_coords.x;
+    let bit_index = block_coords.y;
 
-@fragment
-fn fragment_main() -> @location(0) vec4f {
-    return vec4f(1, 0, 0, 1);
+    let mask = 1u << bit_index;
+    return u32((in_state[block_index] & mask) != 0u);
 }
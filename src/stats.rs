@@ -0,0 +1,294 @@
+use crate::direct_dispatch_size;
+
+/// The source of the reduction shader used to compute [`GridStats`].
+const STATS_SHADER_SOURCE: &str = include_str!("stats.wgsl");
+
+/// The number of `u32`s at the start of the stats buffer, before the row and
+/// column counts. Must match `Stats` in the shader.
+const HEADER_LEN: u32 = 5;
+
+/// Which of the optional statistics to compute in
+/// [`LifeSimulation::read_stats`].
+///
+/// The population and bounding box are always computed. The row and column
+/// counts each need another pass over the grid, and add one `u32` per row or
+/// column to the data read back from the GPU.
+///
+/// [`LifeSimulation::read_stats`]: crate::LifeSimulation::read_stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StatsOptions {
+    /// Count the live cells in each row.
+    pub row_counts: bool,
+
+    /// Count the live cells in each column.
+    pub column_counts: bool,
+}
+
+impl StatsOptions {
+    /// Only computes the population and bounding box.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to count the live cells in each row.
+    pub fn row_counts(mut self, row_counts: bool) -> Self {
+        self.row_counts = row_counts;
+        self
+    }
+
+    /// Sets whether to count the live cells in each column.
+    pub fn column_counts(mut self, column_counts: bool) -> Self {
+        self.column_counts = column_counts;
+        self
+    }
+}
+
+/// Statistics about the live cells in a grid, returned by
+/// [`LifeSimulation::read_stats`].
+///
+/// [`LifeSimulation::read_stats`]: crate::LifeSimulation::read_stats
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GridStats {
+    /// The number of live cells.
+    pub population: u64,
+
+    /// The smallest rectangle containing every live cell, or `None` if there
+    /// are no live cells.
+    pub bounds: Option<Bounds>,
+
+    /// The number of live cells in each row, if requested.
+    pub row_counts: Option<Vec<u32>>,
+
+    /// The number of live cells in each column, if requested.
+    pub column_counts: Option<Vec<u32>>,
+}
+
+/// A rectangle of cells with its top left corner at `x`, `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The pipelines and buffers used to compute [`GridStats`] on the GPU.
+pub(crate) struct StatsPipeline {
+    stats_pipeline: wgpu::ComputePipeline,
+    rows_pipeline: wgpu::ComputePipeline,
+    columns_pipeline: wgpu::ComputePipeline,
+
+    /// Bind groups reading from each of the state buffers, in the same order
+    /// as `LifeSimulation::state_bufs`.
+    bind_groups: [wgpu::BindGroup; 2],
+
+    /// The buffer the results are accumulated into, laid out as described by
+    /// `Stats` in the shader.
+    buffer: wgpu::Buffer,
+
+    grid_size: [u32; 2],
+    physical_grid_size: [u32; 2],
+}
+
+impl StatsPipeline {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        state_bufs: &[wgpu::Buffer; 2],
+        physical_grid_size_buffer: &wgpu::Buffer,
+        grid_size: [u32; 2],
+        physical_grid_size: [u32; 2],
+    ) -> Self {
+        let [width, height] = grid_size;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Stats Buffer"),
+            size: (HEADER_LEN + height + width) as u64 * size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Stats Bind Group Layout"),
+            entries: &[
+                // state
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // physical_grid_size
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // stats
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let create_bind_group = |label, state_buf: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: state_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: physical_grid_size_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let bind_groups = [
+            create_bind_group("Stats Bind Group A", &state_bufs[0]),
+            create_bind_group("Stats Bind Group B", &state_bufs[1]),
+        ];
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Stats Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Stats Shader"),
+            source: wgpu::ShaderSource::Wgsl(STATS_SHADER_SOURCE.into()),
+        });
+        let create_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            stats_pipeline: create_pipeline("Stats Pipeline", "stats_main"),
+            rows_pipeline: create_pipeline("Row Counts Pipeline", "stats_rows"),
+            columns_pipeline: create_pipeline("Column Counts Pipeline", "stats_columns"),
+            bind_groups,
+            buffer,
+            grid_size,
+            physical_grid_size,
+        }
+    }
+
+    /// The buffer holding the results of the last reduction encoded with
+    /// [`Self::encode`].
+    pub(crate) fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// The number of `u32`s of [`Self::buffer`] holding results for
+    /// `options`.
+    pub(crate) fn len(&self, options: StatsOptions) -> u32 {
+        let [width, height] = self.grid_size;
+        if options.column_counts {
+            HEADER_LEN + height + width
+        } else if options.row_counts {
+            HEADER_LEN + height
+        } else {
+            HEADER_LEN
+        }
+    }
+
+    /// Encodes the passes computing the statistics for the state buffer with
+    /// index `state`.
+    pub(crate) fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        state: usize,
+        options: StatsOptions,
+    ) {
+        encoder.clear_buffer(&self.buffer, 0, None);
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Stats Compute Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, &self.bind_groups[state], &[]);
+
+        let max = device.limits().max_compute_workgroups_per_dimension;
+        let [physical_width, physical_height] = self.physical_grid_size;
+        let mut dispatch = |pipeline, invocations| {
+            let [x, y] = direct_dispatch_size(invocations, max);
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.dispatch_workgroups(x, y, 1);
+        };
+
+        dispatch(&self.stats_pipeline, physical_width * physical_height);
+        if options.row_counts {
+            dispatch(&self.rows_pipeline, physical_height);
+        }
+        if options.column_counts {
+            dispatch(&self.columns_pipeline, physical_width * 32);
+        }
+    }
+
+    /// Converts the start of the stats buffer, as copied back from the GPU,
+    /// into [`GridStats`].
+    pub(crate) fn unpack(
+        grid_size: [u32; 2],
+        options: StatsOptions,
+    ) -> impl FnOnce(&[u32]) -> GridStats + Send + 'static {
+        move |data| {
+            let [width, height] = grid_size;
+            let [population, inverted_min_x, inverted_min_y, max_x, max_y] =
+                data[..HEADER_LEN as usize].try_into().unwrap();
+            let counts = &data[HEADER_LEN as usize..];
+
+            let bounds = (population != 0).then(|| {
+                let [x, y] = [!inverted_min_x, !inverted_min_y];
+                Bounds {
+                    x,
+                    y,
+                    width: max_x - x + 1,
+                    height: max_y - y + 1,
+                }
+            });
+
+            GridStats {
+                population: population as u64,
+                bounds,
+                row_counts: options
+                    .row_counts
+                    .then(|| counts[..height as usize].to_vec()),
+                column_counts: options
+                    .column_counts
+                    .then(|| counts[height as usize..(height + width) as usize].to_vec()),
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Reductions over the simulation state, used by `LifeSimulation::read_stats`.
+//
+// The results are accumulated into `stats` with atomics, so the buffer has to
+// be cleared before each reduction. Every field starts at 0, which is why the
+// minimum coordinates are stored inverted.
+
+@group(0) @binding(0) var<storage> state: array<u32>;
+@group(0) @binding(1) var<uniform> physical_grid_size: vec2u;
+
+struct Stats {
+    population: atomic<u32>,
+
+    // `~min_x` and `~min_y`, so that the smallest coordinate has the largest
+    // value and can be found with `atomicMax`.
+    inverted_min_x: atomic<u32>,
+    inverted_min_y: atomic<u32>,
+    max_x: atomic<u32>,
+    max_y: atomic<u32>,
+
+    // The number of live cells in each row, followed by the number of live
+    // cells in each column. Only filled in by `stats_rows` and
+    // `stats_columns`.
+    counts: array<u32>,
+}
+
+@group(0) @binding(2) var<storage, read_write> stats: Stats;
+
+// Must match `WORKGROUP_SIZE` in lib.rs.
+const WORKGROUP_SIZE: u32 = 64u;
+
+var<workgroup> local_population: atomic<u32>;
+var<workgroup> local_inverted_min_x: atomic<u32>;
+var<workgroup> local_inverted_min_y: atomic<u32>;
+var<workgroup> local_max_x: atomic<u32>;
+var<workgroup> local_max_y: atomic<u32>;
+
+// Counts the live cells and finds their bounding box, with one invocation per
+// block. Each workgroup combines the results of its blocks first, so there's
+// only one set of atomics on `stats` per workgroup.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn stats_main(
+    @builtin(global_invocation_id) invocation: vec3u,
+    @builtin(num_workgroups) num_workgroups: vec3u,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let block_index = dispatch_index(invocation, num_workgroups);
+
+    // Invocations past the end of the grid can't return early, since every
+    // invocation has to reach the barrier.
+    var block = 0u;
+    if block_index < physical_grid_size.x * physical_grid_size.y {
+        block = state[block_index];
+    }
+
+    if block != 0u {
+        let x = (block_index % physical_grid_size.x) * 32u;
+        let y = block_index / physical_grid_size.x;
+
+        atomicAdd(&local_population, countOneBits(block));
+        atomicMax(&local_inverted_min_x, ~(x + firstTrailingBit(block)));
+        atomicMax(&local_inverted_min_y, ~y);
+        atomicMax(&local_max_x, x + firstLeadingBit(block));
+        atomicMax(&local_max_y, y);
+    }
+
+    workgroupBarrier();
+
+    if local_index != 0u {
+        return;
+    }
+
+    let population = atomicLoad(&local_population);
+    if population == 0u {
+        return;
+    }
+
+    atomicAdd(&stats.population, population);
+    atomicMax(&stats.inverted_min_x, atomicLoad(&local_inverted_min_x));
+    atomicMax(&stats.inverted_min_y, atomicLoad(&local_inverted_min_y));
+    atomicMax(&stats.max_x, atomicLoad(&local_max_x));
+    atomicMax(&stats.max_y, atomicLoad(&local_max_y));
+}
+
+// Counts the live cells in each row, with one invocation per row.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn stats_rows(
+    @builtin(global_invocation_id) invocation: vec3u,
+    @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+    let row = dispatch_index(invocation, num_workgroups);
+    if row >= physical_grid_size.y {
+        return;
+    }
+
+    var count = 0u;
+    let start = row * physical_grid_size.x;
+    for (var col = 0u; col < physical_grid_size.x; col++) {
+        count += countOneBits(state[start + col]);
+    }
+
+    stats.counts[row] = count;
+}
+
+// Counts the live cells in each column, with one invocation per column of
+// cells. Neighboring invocations read the same blocks, so the loads are shared
+// between them.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn stats_columns(
+    @builtin(global_invocation_id) invocation: vec3u,
+    @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+    let x = dispatch_index(invocation, num_workgroups);
+    let block_col = x / 32u;
+    if block_col >= physical_grid_size.x {
+        return;
+    }
+
+    var count = 0u;
+    let bit = x % 32u;
+    for (var row = 0u; row < physical_grid_size.y; row++) {
+        count += (state[row * physical_grid_size.x + block_col] >> bit) & 1u;
+    }
+
+    // The padding past the right edge of the grid is always dead, so the
+    // extra columns in the last block don't need to be written.
+    if count != 0u {
+        stats.counts[physical_grid_size.y + x] = count;
+    }
+}
+
+// Flattens the invocation ID of a dispatch that wraps onto several rows of
+// workgroups, see `direct_dispatch_size` in lib.rs.
+fn dispatch_index(invocation: vec3u, num_workgroups: vec3u) -> u32 {
+    return invocation.y * num_workgroups.x * WORKGROUP_SIZE + invocation.x;
+}
@@ -0,0 +1,202 @@
+use std::{fmt, str::FromStr};
+
+/// The shape of the surface the grid lives on, i.e. what's on the other side
+/// of the edges of the grid.
+///
+/// These follow the bounded grids supported by Golly. A pair of edges can be
+/// joined normally, so that leaving through one edge re-enters through the
+/// opposite one, or joined with a twist, so that the position along the edge
+/// is also mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Both pairs of opposite edges are joined normally.
+    #[default]
+    Torus,
+
+    /// The edges aren't joined, and cells outside of the grid are always dead.
+    Plane,
+
+    /// One pair of edges is joined with a twist, and the other pair is joined
+    /// normally.
+    KleinBottle {
+        /// The pair of edges that is twisted.
+        twisted: Edges,
+
+        /// Shift applied along the twisted edges after mirroring, wrapping
+        /// around the length of the edge.
+        shift: u32,
+    },
+
+    /// Both pairs of opposite edges are joined with a twist.
+    ///
+    /// A cell beyond one of the corners of the grid crosses both pairs of
+    /// edges, so the corner cells end up being their own neighbors.
+    CrossSurface,
+}
+
+/// A pair of opposite edges of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edges {
+    /// The top and bottom edges.
+    Horizontal,
+
+    /// The left and right edges.
+    Vertical,
+}
+
+impl Topology {
+    // Flags for the `twisted` field of the `Topology` uniform in the shader.
+    const TWIST_HORIZONTAL: u32 = 1 << 0;
+    const TWIST_VERTICAL: u32 = 1 << 1;
+
+    /// Maps cell coordinates that may lie outside of a `grid_size` grid back
+    /// onto the grid.
+    ///
+    /// Returns `None` if the coordinates refer to a cell that is always dead.
+    /// This matches the neighbor lookup done in the compute shader.
+    pub fn resolve(&self, grid_size: [u32; 2], x: i64, y: i64) -> Option<[u32; 2]> {
+        let [width, height] = grid_size.map(i64::from);
+        let [joined, twisted, shift, _] = self.to_uniform();
+
+        let outside_x = !(0..width).contains(&x);
+        let outside_y = !(0..height).contains(&y);
+        if (outside_x || outside_y) && joined == 0 {
+            return None;
+        }
+
+        let (mut x, mut y) = (x, y);
+
+        // Cross the left or right edges first. Coordinates far enough outside
+        // the grid cross the edges several times, and crossing a twisted edge
+        // twice cancels out the mirroring.
+        //
+        // Mirroring `y` may move it off the grid (because of the shift, or
+        // because we're beyond a corner), in which case it gets wrapped when
+        // crossing the top or bottom edges.
+        if outside_x {
+            let crossings = x.div_euclid(width);
+            x = x.rem_euclid(width);
+            if twisted & Self::TWIST_VERTICAL != 0 && crossings % 2 != 0 {
+                y = height - 1 - y + shift as i64;
+            }
+        }
+
+        if !(0..height).contains(&y) {
+            let crossings = y.div_euclid(height);
+            y = y.rem_euclid(height);
+            if twisted & Self::TWIST_HORIZONTAL != 0 && crossings % 2 != 0 {
+                x = (width - 1 - x + shift as i64).rem_euclid(width);
+            }
+        }
+
+        Some([x as u32, y as u32])
+    }
+
+    /// Returns the topology in the layout expected by the `topology` uniform in
+    /// the compute shader, i.e. `[joined, twisted, shift, padding]`.
+    pub fn to_uniform(&self) -> [u32; 4] {
+        match *self {
+            Topology::Torus => [1, 0, 0, 0],
+            Topology::Plane => [0, 0, 0, 0],
+            Topology::KleinBottle {
+                twisted: Edges::Horizontal,
+                shift,
+            } => [1, Self::TWIST_HORIZONTAL, shift, 0],
+            Topology::KleinBottle {
+                twisted: Edges::Vertical,
+                shift,
+            } => [1, Self::TWIST_VERTICAL, shift, 0],
+            Topology::CrossSurface => [1, Self::TWIST_HORIZONTAL | Self::TWIST_VERTICAL, 0, 0],
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Topology::Torus => f.write_str("torus"),
+            Topology::Plane => f.write_str("plane"),
+            Topology::KleinBottle { twisted, shift } => {
+                f.write_str(match twisted {
+                    Edges::Horizontal => "klein-bottle-horizontal",
+                    Edges::Vertical => "klein-bottle-vertical",
+                })?;
+                if shift != 0 {
+                    write!(f, "+{shift}")?;
+                }
+                Ok(())
+            }
+            Topology::CrossSurface => f.write_str("cross-surface"),
+        }
+    }
+}
+
+impl FromStr for Topology {
+    type Err = ParseTopologyError;
+
+    /// Parses the name of a topology, as written by the `Display`
+    /// implementation, in any case.
+    ///
+    /// The names are `torus`, `plane`, `cross-surface`,
+    /// `klein-bottle-horizontal` and `klein-bottle-vertical`, with
+    /// `klein-bottle` being short for the horizontal variant. Klein bottles
+    /// can be followed by a shift, e.g. `klein-bottle-vertical+3`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        let (name, shift) = match name.split_once('+') {
+            Some((name, shift)) => {
+                let shift = shift
+                    .parse()
+                    .map_err(|_| ParseTopologyError::InvalidShift(shift.to_owned()))?;
+                (name, Some(shift))
+            }
+            None => (name.as_str(), None),
+        };
+
+        let twisted = match name {
+            "torus" | "plane" | "cross-surface" if shift.is_some() => {
+                return Err(ParseTopologyError::UnexpectedShift);
+            }
+            "torus" => return Ok(Topology::Torus),
+            "plane" => return Ok(Topology::Plane),
+            "cross-surface" => return Ok(Topology::CrossSurface),
+            "klein-bottle" | "klein-bottle-horizontal" => Edges::Horizontal,
+            "klein-bottle-vertical" => Edges::Vertical,
+            _ => return Err(ParseTopologyError::UnknownTopology(name.to_owned())),
+        };
+
+        Ok(Topology::KleinBottle {
+            twisted,
+            shift: shift.unwrap_or(0),
+        })
+    }
+}
+
+/// The error returned when parsing a [`Topology`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTopologyError {
+    /// The name isn't one of the supported topologies.
+    UnknownTopology(String),
+
+    /// The shift after the `+` isn't a non-negative integer.
+    InvalidShift(String),
+
+    /// A shift was given for a topology without twisted edges.
+    UnexpectedShift,
+}
+
+impl fmt::Display for ParseTopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTopology(name) => write!(
+                f,
+                "unknown topology {name:?}, expected torus, plane, klein-bottle-horizontal, \
+                klein-bottle-vertical or cross-surface",
+            ),
+            Self::InvalidShift(shift) => write!(f, "invalid shift {shift:?}"),
+            Self::UnexpectedShift => write!(f, "only Klein bottles can have a shift"),
+        }
+    }
+}
+
+impl std::error::Error for ParseTopologyError {}
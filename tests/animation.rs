@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use wgpu_gol::{Animation, AnimationFormat, Palette};
+
+use common::{GLIDER_1, GLIDER_3};
+
+mod common;
+
+#[test]
+fn gif() {
+    let gpu = common::require_gpu!();
+
+    let mut sim = gpu.simulation(8, 8, GLIDER_1);
+    let animation = Animation::new(5)
+        .stride(2)
+        .scale(2)
+        .frame_delay(Duration::from_millis(50));
+    assert_eq!(animation.frames(), 3);
+
+    let mut gif = Vec::new();
+    animation.record(&mut sim, &mut gif).unwrap();
+
+    // The simulation stops at the last frame, rather than running the
+    // generation left over after it.
+    assert_eq!(sim.step, 4);
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif.as_slice()).unwrap();
+    assert_eq!([decoder.width(), decoder.height()], [16, 16]);
+
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(frame.delay, 5);
+        frames.push(frame.buffer.to_vec());
+    }
+    assert_eq!(frames.len(), 3);
+
+    // Live cells use the first color of the palette, and dead cells the
+    // second.
+    let expected = |cells: &[u8]| {
+        (0..16 * 16)
+            .map(|index| {
+                let [x, y] = [index % 16 / 2, index / 16 / 2];
+                if cells[y * 8 + x] != 0 { 0 } else { 1 }
+            })
+            .collect::<Vec<u8>>()
+    };
+    assert_eq!(frames[0], expected(GLIDER_1));
+    assert_eq!(frames[1], expected(GLIDER_3));
+}
+
+#[test]
+fn apng() {
+    let gpu = common::require_gpu!();
+
+    let mut sim = gpu.simulation(8, 8, GLIDER_1);
+    let palette = Palette {
+        alive: [255, 128, 0, 255],
+        ..Default::default()
+    };
+    let animation = Animation::new(4)
+        .scale(3)
+        .palette(palette)
+        .format(AnimationFormat::Apng);
+
+    let mut png = Vec::new();
+    animation.record(&mut sim, &mut png).unwrap();
+    assert_eq!(sim.step, 4);
+
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let info = reader.info();
+    assert_eq!([info.width, info.height], [24, 24]);
+    assert_eq!(info.animation_control().unwrap().num_frames, 5);
+
+    // The first frame is the initial state.
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(&pixels[..4], palette.dead);
+    assert_eq!(&pixels[2 * 3 * 4..2 * 3 * 4 + 4], palette.alive);
+}
+
+#[test]
+fn animation_format() {
+    assert_eq!(
+        AnimationFormat::from_extension("gif"),
+        Some(AnimationFormat::Gif)
+    );
+    assert_eq!(
+        AnimationFormat::from_extension("PNG"),
+        Some(AnimationFormat::Apng)
+    );
+    assert_eq!(
+        AnimationFormat::from_extension("apng"),
+        Some(AnimationFormat::Apng)
+    );
+    assert_eq!(AnimationFormat::from_extension("mp4"), None);
+}
@@ -0,0 +1,218 @@
+//! Helpers shared by the integration tests.
+//!
+//! The GPU tests all run on one device, requested the first time a test needs
+//! it. The adapter can be picked with the `WGPU_BACKEND`, `WGPU_POWER_PREF`
+//! and `WGPU_FORCE_FALLBACK_ADAPTER` environment variables, e.g. setting
+//! `WGPU_FORCE_FALLBACK_ADAPTER=1` runs the tests on a software adapter such
+//! as lavapipe. If no adapter is available the GPU tests are skipped.
+
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+use wgpu_gol::{LifeSimulation, PackedGrid, SimError, SimulationConfig};
+
+#[rustfmt::skip]
+pub static GLIDER_1: &[u8] = &[
+    0, 0, 1, 0, 0, 0, 0, 0,
+    1, 0, 1, 0, 0, 0, 0, 0,
+    0, 1, 1, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+#[rustfmt::skip]
+pub static GLIDER_2: &[u8] = &[
+    0, 1, 0, 0, 0, 0, 0, 0,
+    0, 0, 1, 1, 0, 0, 0, 0,
+    0, 1, 1, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+#[rustfmt::skip]
+pub static GLIDER_3: &[u8] = &[
+    0, 0, 1, 0, 0, 0, 0, 0,
+    0, 0, 0, 1, 0, 0, 0, 0,
+    0, 1, 1, 1, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// Gets the shared GPU, or returns from the calling test if there's no adapter
+/// to run it on.
+macro_rules! require_gpu {
+    () => {
+        match common::gpu() {
+            Some(gpu) => gpu,
+            None => return,
+        }
+    };
+}
+
+pub(crate) use require_gpu;
+
+/// The device shared by all of the GPU tests.
+pub struct Gpu {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+/// The config used to pick the adapter for the tests.
+pub fn adapter_config() -> SimulationConfig {
+    SimulationConfig::new().with_env()
+}
+
+/// Returns the shared GPU, requesting it the first time it's used, or `None`
+/// if no adapter is available.
+pub fn gpu() -> Option<&'static Gpu> {
+    static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+
+    let gpu = GPU.get_or_init(
+        || match pollster::block_on(adapter_config().request_device()) {
+            Ok((device, queue)) => Some(Gpu { device, queue }),
+            Err(SimError::NoAdapter(err)) => {
+                eprintln!("No adapter available, skipping GPU tests: {err}");
+                None
+            }
+            Err(err) => panic!("Failed to request device: {err}"),
+        },
+    );
+
+    if gpu.is_none() {
+        eprintln!("Skipping test, no adapter available");
+    }
+    gpu.as_ref()
+}
+
+impl Gpu {
+    /// Creates a simulation with the default config, like
+    /// [`LifeSimulation::new`].
+    pub fn simulation(&self, width: u32, height: u32, initial_state: &[u8]) -> LifeSimulation {
+        self.simulation_with_config(width, height, initial_state, SimulationConfig::default())
+    }
+
+    /// Creates a simulation, like [`LifeSimulation::with_config`].
+    pub fn simulation_with_config(
+        &self,
+        width: u32,
+        height: u32,
+        initial_state: &[u8],
+        config: SimulationConfig,
+    ) -> LifeSimulation {
+        self.try_simulation(width, height, initial_state, config)
+            .expect("Failed to create simulation")
+    }
+
+    /// Creates a simulation, like [`LifeSimulation::try_new`].
+    pub fn try_simulation(
+        &self,
+        width: u32,
+        height: u32,
+        initial_state: &[u8],
+        config: SimulationConfig,
+    ) -> Result<LifeSimulation, SimError> {
+        pollster::block_on(LifeSimulation::from_device(
+            &self.device,
+            &self.queue,
+            width,
+            height,
+            initial_state,
+            config,
+        ))
+    }
+
+    /// Creates a simulation starting from `grid`, like
+    /// [`LifeSimulation::try_from_grid`].
+    pub fn try_simulation_from_grid(
+        &self,
+        grid: &PackedGrid,
+        config: SimulationConfig,
+    ) -> Result<LifeSimulation, SimError> {
+        pollster::block_on(LifeSimulation::from_device_with_grid(
+            &self.device,
+            &self.queue,
+            grid,
+            config,
+        ))
+    }
+}
+
+pub fn assert_grid_eq(width: usize, expected: &[u8], actual: &[u8]) {
+    assert_eq!(expected.len() % width, 0);
+    assert_eq!(actual.len(), expected.len());
+
+    if expected != actual {
+        eprintln!("Grids do not match!");
+        eprintln!("Expected grid: [");
+        for row in expected.chunks(width) {
+            eprintln!("{:?}", row);
+        }
+        eprintln!("]");
+
+        eprintln!("Actual grid: [");
+        for row in actual.chunks(width) {
+            eprintln!("{:?}", row);
+        }
+        eprintln!("]");
+
+        panic!("assert_grid_eq failed: grids do not match");
+    }
+}
+
+/// Copies a smaller 8x8 grid into a larger grid at the specified offset,
+/// wrapping around the edges of the larger grid.
+pub fn copy_to_grid(src: &[u8], dst: &mut [u8], dst_width: usize, offset: [usize; 2]) {
+    assert_eq!(src.len(), 8 * 8);
+    assert_eq!(dst.len() % dst_width, 0);
+
+    let dst_height = dst.len() / dst_width;
+    let [x_offset, y_offset] = offset;
+
+    for row in 0..8 {
+        for col in 0..8 {
+            let dst_row = (row + y_offset) % dst_height;
+            let dst_col = (col + x_offset) % dst_width;
+            dst[dst_row * dst_width + dst_col] = src[row * 8 + col];
+        }
+    }
+}
+
+pub fn do_step(sim: &mut LifeSimulation) {
+    let mut encoder = sim
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+    sim.encode_compute_pass(&mut encoder);
+
+    sim.queue.submit([encoder.finish()]);
+    sim.device
+        .poll(wgpu::PollType::Wait)
+        .expect("Failed to poll device");
+}
+
+pub fn do_steps(sim: &mut LifeSimulation, generations: u64) {
+    let mut encoder = sim
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+    sim.encode_steps(&mut encoder, generations);
+
+    sim.queue.submit([encoder.finish()]);
+    sim.device
+        .poll(wgpu::PollType::Wait)
+        .expect("Failed to poll device");
+}
@@ -0,0 +1,493 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use wgpu_gol::{
+    HashLife, PackedGrid, ParsePatternError, Pattern, PatternFormat, Rule, SimulationConfig,
+    Topology,
+};
+
+use common::{GLIDER_1, GLIDER_2, assert_grid_eq, copy_to_grid, do_step, do_steps};
+
+mod common;
+
+#[test]
+fn packed_grid() {
+    let gpu = common::require_gpu!();
+
+    let mut grid = PackedGrid::new(40, 3);
+    assert_eq!(grid.physical_size(), [2, 3]);
+    assert_eq!(grid.count(), 0);
+
+    grid.set(0, 0, true);
+    grid.set(31, 1, true);
+    grid.set(32, 1, true);
+    grid.set(39, 2, true);
+    assert!(grid.get(31, 1) && grid.get(32, 1) && !grid.get(33, 1));
+    assert_eq!(grid.count(), 4);
+    assert_eq!(
+        grid.live_cells().collect::<Vec<_>>(),
+        [[0, 0], [31, 1], [32, 1], [39, 2]],
+    );
+
+    grid.set(0, 0, false);
+    assert_eq!(grid.count(), 3);
+
+    // Converting to and from bytes and blocks round trips.
+    let cells = grid.to_cells();
+    assert_eq!(cells.len(), 40 * 3);
+    assert_eq!(grid.iter().map(u8::from).collect::<Vec<_>>(), cells);
+    assert_eq!(PackedGrid::from_cells(40, 3, &cells), grid);
+
+    // Bits in the padding past the right edge are dropped.
+    let mut blocks = grid.blocks().to_vec();
+    blocks[1] |= 1 << 31;
+    assert_eq!(PackedGrid::from_blocks(40, 3, blocks), grid);
+
+    // A simulation can start from and be read back as a packed grid.
+    let mut init_state = PackedGrid::new(8, 8);
+    for [x, y] in PackedGrid::from_cells(8, 8, GLIDER_1).live_cells() {
+        init_state.set(x, y, true);
+    }
+    let mut sim = gpu
+        .try_simulation_from_grid(&init_state, SimulationConfig::default())
+        .unwrap();
+    assert_eq!(sim.read_grid(), init_state);
+
+    do_step(&mut sim);
+    assert_eq!(sim.read_grid(), PackedGrid::from_cells(8, 8, GLIDER_2));
+
+    sim.reset_grid(&init_state);
+    assert_eq!(sim.step, 0);
+    assert_grid_eq(8, GLIDER_1, &sim.read_state());
+}
+
+#[test]
+fn paste() {
+    let glider = PackedGrid::from_cells(8, 8, GLIDER_1);
+
+    // Pasting into an empty grid places the live cells at the offset.
+    let mut grid = PackedGrid::new(40, 30);
+    grid.paste(3, 4, &glider);
+    assert_eq!(
+        grid.live_cells().collect::<Vec<_>>(),
+        [[5, 4], [3, 5], [5, 5], [4, 6], [5, 6]],
+    );
+
+    // Dead cells are copied too, clearing the live cells they overlap.
+    let mut grid = PackedGrid::random(40, 30, 1.0, &mut StdRng::seed_from_u64(22));
+    grid.paste(30, 20, &glider);
+    assert_eq!(grid.count(), 40 * 30 - 64 + 5);
+    assert!(!grid.get(30, 20) && grid.get(32, 20) && grid.get(29, 20));
+    assert!(grid.get(38, 28) && grid.get(30, 28));
+
+    // A grid of the same size fits exactly.
+    let mut grid = PackedGrid::new(8, 8);
+    grid.paste(0, 0, &glider);
+    assert_eq!(grid, glider);
+}
+
+#[test]
+#[should_panic(expected = "doesn't fit")]
+fn paste_out_of_bounds() {
+    let mut grid = PackedGrid::new(40, 30);
+    grid.paste(33, 0, &PackedGrid::from_cells(8, 8, GLIDER_1));
+}
+
+#[test]
+fn random_grid() {
+    let soup =
+        |density, seed| PackedGrid::random(40, 30, density, &mut StdRng::seed_from_u64(seed));
+    assert_eq!(soup(0.5, 21).size(), [40, 30]);
+
+    // The density is the chance of each cell being alive.
+    assert_eq!(soup(0.0, 21).count(), 0);
+    assert_eq!(soup(1.0, 21).count(), 40 * 30);
+    assert!((400..800).contains(&soup(0.5, 21).count()));
+    assert!((60..240).contains(&soup(0.125, 21).count()));
+
+    // Soups are reproducible from the seed, and differ between seeds.
+    assert_eq!(soup(0.5, 21), soup(0.5, 21));
+    assert_ne!(soup(0.5, 21), soup(0.5, 22));
+}
+
+#[test]
+fn rle() {
+    let gpu = common::require_gpu!();
+
+    let glider = Pattern::from_rle(
+        "#N Glider
+#C The smallest spaceship.
+x = 3, y = 3, rule = B3/S23
+2bo$obo$b2o!",
+    )
+    .unwrap();
+    assert_eq!(glider.name.as_deref(), Some("Glider"));
+    assert_eq!(glider.comments, ["The smallest spaceship."]);
+    assert_eq!(glider.rule, Some(Rule::CONWAY));
+    assert_eq!(glider.grid.size(), [3, 3]);
+    assert_eq!(
+        glider.grid.live_cells().collect::<Vec<_>>(),
+        [[2, 0], [0, 1], [2, 1], [1, 2], [2, 2]],
+    );
+
+    // Writing the pattern back out gives the same text.
+    assert_eq!(
+        glider.to_rle(),
+        "#N Glider
+#C The smallest spaceship.
+x = 3, y = 3, rule = B3/S23
+2bo$obo$b2o!
+",
+    );
+
+    // Runs can be split across lines, blank rows are skipped with a count
+    // before `$`, and the header can be missing, in which case the pattern is
+    // just large enough for its cells. Golly's bounded grid suffix is ignored.
+    let pattern = Pattern::from_rle("3o2$\n1\n2b\no !").unwrap();
+    assert_eq!(pattern.grid.size(), [13, 3]);
+    assert_eq!(
+        pattern.grid.live_cells().collect::<Vec<_>>(),
+        [[0, 0], [1, 0], [2, 0], [12, 2]],
+    );
+    let pattern = Pattern::from_rle("x = 5, y = 2, rule = 23/36:T5,2\no!").unwrap();
+    assert_eq!(pattern.grid.size(), [5, 2]);
+    assert_eq!(pattern.rule, Some("B36/S23".parse().unwrap()));
+
+    // Comments of an unknown kind are skipped, even when they start with a
+    // character that isn't ASCII.
+    let pattern = Pattern::from_rle("#é\n#→ note\nx = 2, y = 1\n2o!").unwrap();
+    assert_eq!(pattern.comments, Vec::<String>::new());
+    assert_eq!(pattern.grid.size(), [2, 1]);
+
+    // Long rows wrap at 70 characters and round trip.
+    let mut rng = StdRng::seed_from_u64(15);
+    let cells = (0..200 * 20)
+        .map(|_| rng.random_range(0..2))
+        .collect::<Vec<u8>>();
+    let pattern = Pattern::new(PackedGrid::from_cells(200, 20, &cells));
+    let text = pattern.to_rle();
+    assert!(text.lines().all(|line| line.len() <= 70));
+    assert_eq!(Pattern::from_rle(&text).unwrap(), pattern);
+
+    assert_eq!(
+        Pattern::from_rle("x = 3, y = 3\nobo$4o!"),
+        Err(ParsePatternError::OutOfBounds { x: 3, y: 1 }),
+    );
+    // Huge runs are rejected without overflowing or expanding them.
+    assert_eq!(
+        Pattern::from_rle("18446744073709551615b2o!"),
+        Err(ParsePatternError::TooLarge {
+            width: u64::MAX,
+            height: 1,
+        }),
+    );
+    assert_eq!(
+        Pattern::from_rle("x = 4, y = 1\n4000000000o!"),
+        Err(ParsePatternError::OutOfBounds { x: 4, y: 0 }),
+    );
+    assert_eq!(
+        Pattern::from_rle("5000000000o!"),
+        Err(ParsePatternError::TooLarge {
+            width: 5000000000,
+            height: 1,
+        }),
+    );
+    assert_eq!(
+        Pattern::from_rle("x = 3, y = 3\nob%!"),
+        Err(ParsePatternError::InvalidCharacter {
+            line: 2,
+            character: '%',
+        }),
+    );
+    assert!(matches!(
+        Pattern::from_rle("x = 3, y = 3, rule = B9/S23\n!"),
+        Err(ParsePatternError::InvalidRule(_)),
+    ));
+    assert!(matches!(
+        Pattern::from_rle("x = 3\n!"),
+        Err(ParsePatternError::InvalidHeader(_)),
+    ));
+
+    // Placing a pattern writes its cells at an offset and switches to its
+    // rule, and it can be read back out of the simulation.
+    let mut sim = gpu.simulation(8, 8, &[0; 8 * 8]);
+    sim.write_pattern(1, 2, &glider.clone().with_rule(Rule::new(&[3, 6], &[2, 3])));
+    assert_eq!(sim.rule, Rule::new(&[3, 6], &[2, 3]));
+    let mut expected = [0; 8 * 8];
+    copy_to_grid(GLIDER_1, &mut expected, 8, [1, 2]);
+    assert_grid_eq(8, &expected, &sim.read_state());
+
+    sim.set_rule(Rule::CONWAY);
+    let region = sim.read_region_pattern(1, 2, 3, 3);
+    assert_eq!(region.grid, glider.grid);
+    assert_eq!(region.rule, Some(Rule::CONWAY));
+
+    do_step(&mut sim);
+    assert_eq!(
+        sim.read_pattern().to_rle(),
+        "x = 8, y = 8, rule = B3/S23\n2$2bo$3b2o$2b2o!\n",
+    );
+}
+
+#[test]
+fn pattern_formats() {
+    let gpu = common::require_gpu!();
+
+    let glider_grid = Pattern::from_rle("x = 3, y = 3\n2bo$obo$b2o!")
+        .unwrap()
+        .grid;
+
+    let plaintext = "!Name: Glider
+!The smallest spaceship.
+..O
+O.O
+.OO
+";
+    let glider = Pattern::from_plaintext(plaintext).unwrap();
+    assert_eq!(glider.grid, glider_grid);
+    assert_eq!(glider.name.as_deref(), Some("Glider"));
+    assert_eq!(glider.comments, ["The smallest spaceship."]);
+    assert_eq!(glider.to_plaintext(), plaintext);
+
+    // Short lines are padded with dead cells, and `*` is also accepted.
+    let pattern = Pattern::from_plaintext("*\n\n..O.\n").unwrap();
+    assert_eq!(pattern.grid.size(), [4, 3]);
+    assert_eq!(
+        pattern.grid.live_cells().collect::<Vec<_>>(),
+        [[0, 0], [2, 2]]
+    );
+
+    // The Life formats use an arbitrary origin, so the pattern is cropped to
+    // its live cells.
+    let life_105 = Pattern::from_life_105(
+        "#Life 1.05
+#D Glider
+#R 23/36
+#P -1 -1
+..*
+*.*
+#P -2 1
+..**
+",
+    )
+    .unwrap();
+    assert_eq!(life_105.grid, glider_grid);
+    assert_eq!(life_105.rule, Some(Rule::new(&[3, 6], &[2, 3])));
+    assert_eq!(life_105.comments, ["Glider"]);
+    assert_eq!(
+        Pattern::from_life_105(&life_105.to_life_105()).unwrap(),
+        life_105
+    );
+    assert_eq!(
+        Pattern::from_life_105("#Life 1.05\n#Ü\n#P 0 0\n**\n")
+            .unwrap()
+            .grid
+            .size(),
+        [2, 1],
+    );
+
+    assert!(matches!(
+        Pattern::from_life_105(&format!("#Life 1.05\n#P {} 0\n.*\n", i64::MAX)),
+        Err(ParsePatternError::TooLarge { .. }),
+    ));
+
+    let life_106 = Pattern::from_life_106("#Life 1.06\n1 -1\n-1 0\n1 0\n0 1\n1 1\n").unwrap();
+    assert_eq!(life_106.grid, glider_grid);
+    assert_eq!(
+        Pattern::from_life_106(&life_106.to_life_106()).unwrap(),
+        life_106,
+    );
+    assert_eq!(
+        Pattern::from_life_106("#Life 1.06\n1 2\n3\n"),
+        Err(ParsePatternError::InvalidCoordinates { line: 3 }),
+    );
+    assert_eq!(
+        Pattern::from_life_106(&format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX)),
+        Err(ParsePatternError::TooLarge {
+            width: u64::MAX,
+            height: 1,
+        }),
+    );
+
+    // Wide patterns are split into blocks that fit in 80 columns.
+    let mut rng = StdRng::seed_from_u64(16);
+    let cells = (0..200 * 20)
+        .map(|_| rng.random_range(0..2))
+        .collect::<Vec<u8>>();
+    let mut grid = PackedGrid::from_cells(200, 20, &cells);
+    for y in 0..20 {
+        grid.set(0, y, true);
+        grid.set(199, y, true);
+    }
+    let pattern = Pattern::new(grid).with_rule(Rule::CONWAY);
+    let text = pattern.to_life_105();
+    assert!(text.lines().all(|line| line.len() <= 80));
+    assert_eq!(Pattern::from_life_105(&text).unwrap(), pattern);
+
+    // Every format is detected from its contents, and parses to the same
+    // grid as the simulation reads and writes.
+    for format in [
+        PatternFormat::Rle,
+        PatternFormat::Plaintext,
+        PatternFormat::Life105,
+        PatternFormat::Life106,
+    ] {
+        let text = pattern.write(format);
+        assert_eq!(PatternFormat::detect(&text), format);
+
+        let parsed = text.parse::<Pattern>().unwrap();
+        assert_eq!(parsed.grid, pattern.grid, "{format:?} didn't round trip");
+
+        let sim = gpu.simulation(200, 20, &parsed.grid.to_cells());
+        assert_eq!(sim.read_grid(), pattern.grid);
+    }
+
+    assert_eq!(PatternFormat::detect("..O\nO.O"), PatternFormat::Plaintext);
+    assert_eq!(PatternFormat::detect("bo$2bo$3o!"), PatternFormat::Rle);
+    assert_eq!(
+        PatternFormat::from_extension("CELLS"),
+        Some(PatternFormat::Plaintext)
+    );
+}
+
+#[test]
+fn hashlife() {
+    let gpu = common::require_gpu!();
+
+    let glider = Pattern::from_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    let mut hashlife = HashLife::from_pattern(&glider);
+    assert_eq!(hashlife.population(), 5);
+    assert!(hashlife.get(1, 0) && !hashlife.get(0, 0));
+
+    // A glider moves one cell diagonally every 4 generations, so after 2^20
+    // generations it has moved 2^18 cells.
+    hashlife.step(4);
+    assert_eq!(hashlife.read_grid(1, 1, 3, 3), glider.grid);
+    hashlife.step_pow2(20);
+    assert_eq!(hashlife.generation(), 4 + (1 << 20));
+    let offset = 1 + (1 << 18);
+    assert_eq!(hashlife.bounds(), Some(([offset, offset], [3, 3])));
+    assert_eq!(hashlife.read_grid(offset, offset, 3, 3), glider.grid);
+
+    // Steps past the largest power of two that can be taken at once are split
+    // up, up to the most generations a step can be given. A block never
+    // changes, however far it's advanced.
+    let block = Pattern::from_rle("2o$2o!").unwrap();
+    let mut still = HashLife::from_pattern(&block);
+    still.step_pow2(59);
+    still.step(1 << 60);
+    assert_eq!(still.generation(), (1 << 59) + (1 << 60));
+    still.step(u64::MAX - (1 << 59) - (1 << 60));
+    assert_eq!(still.generation(), u64::MAX);
+    assert_eq!(still.to_pattern(), (block.with_rule(Rule::CONWAY), [0, 0]));
+
+    // Setting cells far away grows the plane, and clearing them leaves the
+    // population as it was.
+    hashlife.set(-1 << 40, 1 << 40, true);
+    assert_eq!(hashlife.population(), 6);
+    hashlife.set(-1 << 40, 1 << 40, false);
+    assert_eq!(hashlife.population(), 5);
+
+    // Matches the GPU on a plane as long as the pattern stays away from the
+    // edges, for both single steps and larger jumps.
+    let mut rng = StdRng::seed_from_u64(17);
+    let rule = Rule::new(&[3, 6], &[2, 3]);
+    let soup = (0..32 * 32)
+        .map(|_| rng.random_range(0..2))
+        .collect::<Vec<u8>>();
+    let mut hashlife = HashLife::from_pattern(
+        &Pattern::new(PackedGrid::from_cells(32, 32, &soup)).with_rule(rule),
+    );
+    let mut init_state = PackedGrid::new(128, 128);
+    for [x, y] in PackedGrid::from_cells(32, 32, &soup).live_cells() {
+        init_state.set(x + 48, y + 48, true);
+    }
+    let mut sim = gpu
+        .try_simulation_from_grid(
+            &init_state,
+            SimulationConfig::new().rule(rule).topology(Topology::Plane),
+        )
+        .unwrap();
+    for generations in [1, 1, 2, 8, 4, 3] {
+        hashlife.step(generations);
+        do_steps(&mut sim, generations);
+        assert_eq!(
+            hashlife.read_grid(-48, -48, 128, 128),
+            sim.read_grid(),
+            "HashLife differs from the GPU at generation {}",
+            hashlife.generation(),
+        );
+    }
+
+    // Golly's glider, with the root centered on the origin so the glider is
+    // in the bottom right quarter.
+    let text = "[M2] (golly 4.2)
+#R B3/S23
+#G 7
+.*$..*$***$
+4 0 0 0 1
+";
+    let hashlife = HashLife::from_macrocell(text).unwrap();
+    assert_eq!(hashlife.generation(), 7);
+    assert_eq!(hashlife.bounds(), Some(([0, 0], [3, 3])));
+    assert_eq!(hashlife.read_grid(0, 0, 3, 3), glider.grid);
+
+    let written = hashlife.to_macrocell();
+    assert!(written.starts_with("[M2]"));
+    let reread = HashLife::from_macrocell(&written).unwrap();
+    assert_eq!(reread.generation(), 7);
+    assert_eq!(reread.to_pattern(), hashlife.to_pattern());
+    let commented = text.replace("#G 7", "#G 7\n#ñ");
+    assert_eq!(
+        HashLife::from_macrocell(&commented).unwrap().to_pattern(),
+        hashlife.to_pattern(),
+    );
+
+    // Macrocell files can be loaded as flat patterns too.
+    assert_eq!(PatternFormat::detect(text), PatternFormat::Macrocell);
+    let pattern = text.parse::<Pattern>().unwrap();
+    assert_eq!(pattern.grid, glider.grid);
+    assert_eq!(
+        Pattern::from_macrocell(&pattern.to_macrocell()).unwrap(),
+        pattern,
+    );
+
+    // A few lines can describe a plane of cells far too large to extract,
+    // which is found without visiting each cell.
+    let mut huge = String::from("[M2]\n*$\n");
+    for level in 4..=60 {
+        let child = level - 3;
+        huge.push_str(&format!("{level} {child} {child} {child} {child}\n"));
+    }
+    let size = (1 << 60) - 7;
+    assert_eq!(
+        HashLife::from_macrocell(&huge).unwrap().bounds(),
+        Some(([-1 << 59, -1 << 59], [size, size])),
+    );
+    assert_eq!(
+        Pattern::from_macrocell(&huge),
+        Err(ParsePatternError::TooLarge {
+            width: size,
+            height: size,
+        }),
+    );
+
+    assert_eq!(
+        HashLife::from_macrocell("[M2]\n#R B03/S23\n").err(),
+        Some(ParsePatternError::UnsupportedRule(
+            "B03/S23".parse().unwrap()
+        )),
+    );
+
+    // Patterns with B0 rules can still be written as macrocell files and read
+    // back, just not run by HashLife.
+    let b0 = glider.clone().with_rule("B03/S23".parse().unwrap());
+    let text = b0.to_macrocell();
+    assert_eq!(Pattern::from_macrocell(&text).unwrap(), b0);
+    assert_eq!(
+        HashLife::from_macrocell(&text).err(),
+        Some(ParsePatternError::UnsupportedRule(b0.rule.unwrap())),
+    );
+    assert_eq!(
+        HashLife::from_macrocell("[M2]\n.*$\n5 0 0 0 1\n").err(),
+        Some(ParsePatternError::InvalidNode { line: 3 }),
+    );
+}
@@ -0,0 +1,164 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use wgpu_gol::{Edges, Kernel, PackedGrid, ReferenceSimulation, Rule, SimulationConfig, Topology};
+
+use common::{Gpu, do_steps};
+
+mod common;
+
+/// Runs random soups of various sizes on the GPU and on the CPU reference
+/// implementation, and checks that they stay identical.
+#[test]
+fn soups() {
+    let gpu = common::require_gpu!();
+
+    let mut rng = StdRng::seed_from_u64(18);
+
+    // Mostly sizes that aren't a multiple of the block size, so that the
+    // padding at the end of each row is exercised.
+    for [width, height] in [
+        [33, 33],
+        [65, 65],
+        [100, 100],
+        [32, 32],
+        [31, 47],
+        [64, 3],
+        [97, 20],
+        [5, 130],
+        [1, 1],
+        [2, 7],
+    ] {
+        let grid = random_grid(&mut rng, width, height);
+        run_differential(gpu, &grid, SimulationConfig::default(), 200);
+    }
+}
+
+/// Like [`soups`], but with every combination of rule, topology and kernel.
+#[test]
+fn configurations() {
+    let gpu = common::require_gpu!();
+
+    let mut rng = StdRng::seed_from_u64(19);
+
+    let rules = [
+        Rule::CONWAY,
+        "B36/S23".parse().unwrap(),
+        "B2/S".parse().unwrap(),
+        "B3678/S34678".parse().unwrap(),
+    ];
+    let topologies = [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::KleinBottle {
+            twisted: Edges::Horizontal,
+            shift: 3,
+        },
+        Topology::KleinBottle {
+            twisted: Edges::Vertical,
+            shift: 0,
+        },
+        Topology::CrossSurface,
+    ];
+    let kernels = [
+        Kernel::Direct,
+        Kernel::Tiled,
+        Kernel::Temporal { generations: 4 },
+    ];
+    for [width, height] in [[33, 65], [100, 37]] {
+        for rule in rules {
+            for topology in topologies {
+                for kernel in kernels {
+                    let grid = random_grid(&mut rng, width, height);
+                    let config = SimulationConfig {
+                        rule,
+                        topology,
+                        kernel,
+                        ..Default::default()
+                    };
+                    run_differential(gpu, &grid, config, 60);
+                }
+            }
+        }
+    }
+}
+
+fn random_grid(rng: &mut StdRng, width: u32, height: u32) -> PackedGrid {
+    let cells = (0..width * height)
+        .map(|_| rng.random_range(0..2))
+        .collect::<Vec<u8>>();
+    PackedGrid::from_cells(width, height, &cells)
+}
+
+/// Runs `grid` for `generations` generations on both the GPU and the CPU
+/// reference implementation, panicking with the first cell that differs and
+/// the generation it differs in.
+#[track_caller]
+fn run_differential(gpu: &Gpu, grid: &PackedGrid, config: SimulationConfig, generations: u64) {
+    // Reading back the GPU state every generation is slow, so the grids are
+    // only compared every few generations. Once they differ, both are rewound
+    // to the last matching state and stepped one generation at a time.
+    const STRIDE: u64 = 10;
+
+    let [width, height] = grid.size();
+    eprintln!(
+        "Comparing {width}x{height} {:?} {} {:?} against the reference",
+        config.kernel, config.rule, config.topology,
+    );
+
+    let (rule, topology) = (config.rule, config.topology);
+    let mut gpu = gpu
+        .try_simulation_from_grid(grid, config)
+        .expect("Failed to create simulation");
+    let mut cpu = ReferenceSimulation::new(grid.clone(), rule, topology);
+
+    let mut last_match = grid.clone();
+    let mut generation = 0;
+    while generation < generations {
+        let batch = STRIDE.min(generations - generation);
+        do_steps(&mut gpu, batch);
+        cpu.steps(batch);
+
+        if gpu.read_grid() == *cpu.grid() {
+            generation += batch;
+            last_match = cpu.grid().clone();
+            continue;
+        }
+
+        gpu.reset_grid(&last_match);
+        cpu = ReferenceSimulation::new(last_match.clone(), rule, topology);
+        for offset in 1..=batch {
+            // A single pass of the temporal kernel runs several generations,
+            // so this has to go through `encode_steps`.
+            do_steps(&mut gpu, 1);
+            cpu.step();
+
+            let actual = gpu.read_grid();
+            let expected = cpu.grid();
+            let Some([x, y]) = first_difference(expected, &actual) else {
+                continue;
+            };
+            panic!(
+                "GPU and reference differ at generation {}, cell ({x}, {y}): \
+                expected {}, got {}",
+                generation + offset,
+                if expected.get(x, y) { "alive" } else { "dead" },
+                if actual.get(x, y) { "alive" } else { "dead" },
+            );
+        }
+
+        panic!(
+            "GPU and reference differ after generation {}, but not when \
+            stepping one generation at a time",
+            generation + batch,
+        );
+    }
+}
+
+/// Returns the first cell in row-major order that differs between two grids
+/// of the same size.
+fn first_difference(expected: &PackedGrid, actual: &PackedGrid) -> Option<[u32; 2]> {
+    assert_eq!(expected.size(), actual.size());
+    let [width, height] = expected.size();
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| [x, y]))
+        .find(|&[x, y]| expected.get(x, y) != actual.get(x, y))
+}
@@ -0,0 +1,283 @@
+use wgpu_gol::{Camera, Image, Palette, Renderer};
+
+use common::{GLIDER_1, GLIDER_2, copy_to_grid, do_step};
+
+mod common;
+
+#[test]
+fn render_image() {
+    let gpu = common::require_gpu!();
+
+    let mut sim = gpu.simulation(8, 8, GLIDER_1);
+    let renderer = Renderer::offscreen(&sim);
+
+    // Each cell is drawn as a 2x2 square of pixels.
+    let image = renderer.render_image(&sim, 16, 16);
+    assert_eq!([image.width(), image.height()], [16, 16]);
+    assert_eq!(image.pixels().len(), 16 * 16 * 4);
+    assert_image_eq(&image, GLIDER_1, 8, [0, 0], 2, Palette::default());
+
+    // The renderer draws whatever generation the simulation is on.
+    do_step(&mut sim);
+    let image = renderer.render_image(&sim, 16, 16);
+    assert_image_eq(&image, GLIDER_2, 8, [0, 0], 2, Palette::default());
+}
+
+#[test]
+fn render_aspect_ratio() {
+    let gpu = common::require_gpu!();
+
+    let mut init_state = [0; 16 * 8];
+    copy_to_grid(GLIDER_1, &mut init_state, 16, [0, 0]);
+    let sim = gpu.simulation(16, 8, &init_state);
+
+    let mut renderer = Renderer::offscreen(&sim);
+    let palette = Palette {
+        alive: [255, 0, 0, 255],
+        dead: [0, 0, 255, 255],
+        background: [0, 255, 0, 255],
+    };
+    renderer.palette = palette;
+
+    // A 16x8 grid in a 32x32 image is drawn with 2x2 cells, centered between
+    // two 8 pixel tall bars of background.
+    let image = renderer.render_image(&sim, 32, 32);
+    assert_image_eq(&image, &init_state, 16, [0, 8], 2, palette);
+    for x in 0..32 {
+        for y in (0..8).chain(24..32) {
+            assert_eq!(image.pixel(x, y), palette.background, "pixel ({x}, {y})");
+        }
+    }
+
+    // The sizes don't have to divide evenly.
+    let image = renderer.render_image(&sim, 35, 13);
+    assert_eq!([image.width(), image.height()], [35, 13]);
+}
+
+#[test]
+fn render_camera() {
+    let gpu = common::require_gpu!();
+
+    let sim = gpu.simulation(8, 8, GLIDER_1);
+    let mut renderer = Renderer::offscreen(&sim);
+    let palette = Palette::default();
+
+    // Zoomed in on the top left 4x4 cells, each drawn as a 4x4 square.
+    let top_left = |size: usize| {
+        GLIDER_1
+            .chunks(8)
+            .take(size)
+            .flat_map(|row| &row[..size])
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    renderer.camera = Some(Camera {
+        center: [2.0, 2.0],
+        cells_per_pixel: 0.25,
+    });
+    let image = renderer.render_image(&sim, 16, 16);
+    assert_image_eq(&image, &top_left(4), 4, [0, 0], 4, palette);
+
+    // Panned so that the corner of the grid is in the middle of the image.
+    renderer.camera.as_mut().unwrap().pan([8.0, 8.0]);
+    let image = renderer.render_image(&sim, 16, 16);
+    assert_image_eq(&image, &top_left(2), 2, [8, 8], 4, palette);
+    for (x, y) in [(0, 0), (7, 7), (15, 0), (0, 15)] {
+        assert_eq!(image.pixel(x, y), palette.background, "pixel ({x}, {y})");
+    }
+}
+
+#[test]
+fn camera() {
+    let target_size = [200, 100];
+
+    // A square fits the height of a wide target, and is centered in it.
+    let mut camera = Camera::fit(10.0, 20.0, 50.0, 50.0, target_size);
+    assert_eq!(camera.center, [35.0, 45.0]);
+    assert_eq!(camera.cells_per_pixel, 0.5);
+    assert_eq!(camera.origin(target_size), [-15.0, 20.0]);
+    assert_eq!(camera.cell_at([100.0, 50.0], target_size), [35.0, 45.0]);
+
+    // Dragging moves the cells along with the cursor.
+    camera.pan([20.0, -10.0]);
+    assert_eq!(camera.cell_at([120.0, 40.0], target_size), [35.0, 45.0]);
+
+    // Zooming keeps the cell under the cursor in place.
+    let cursor = [30.0, 70.0];
+    let cell = camera.cell_at(cursor, target_size);
+    camera.zoom_at(4.0, cursor, target_size);
+    assert_eq!(camera.cells_per_pixel, 0.125);
+    assert_eq!(camera.cell_at(cursor, target_size), cell);
+}
+
+#[test]
+fn write_png() {
+    let gpu = common::require_gpu!();
+
+    let sim = gpu.simulation(8, 8, GLIDER_1);
+    let image = Renderer::offscreen(&sim).render_image(&sim, 24, 8);
+
+    let mut png = Vec::new();
+    image.write_png(&mut png).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let path = std::env::temp_dir().join(format!("wgpu-gol-{}.png", std::process::id()));
+    image.save_png(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), png);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn custom_render_pipeline() {
+    let gpu = common::require_gpu!();
+
+    // Applications can read the state from their own fragment shaders through
+    // the simulation's bind group layout, here drawing one pixel per cell.
+    const SHADER: &str = "
+        @group(0) @binding(1) var<storage> in_state: array<u32>;
+        @group(0) @binding(3) var<uniform> physical_grid_size: vec2u;
+
+        @vertex
+        fn vertex_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+            let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+            return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+        }
+
+        @fragment
+        fn fragment_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+            let cell = vec2u(position.xy);
+            let block = in_state[cell.y * physical_grid_size.x + cell.x / 32u];
+            return vec4f(f32((block >> (cell.x % 32u)) & 1u), 0.0, 0.0, 1.0);
+        }
+    ";
+
+    let mut sim = gpu.simulation(8, 8, GLIDER_1);
+    do_step(&mut sim);
+
+    let device = &sim.device;
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Custom Shader"),
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Custom Pipeline Layout"),
+        bind_group_layouts: &[&sim.bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Custom Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex_main"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fragment_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    let size = wgpu::Extent3d {
+        width: 8,
+        height: 8,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Custom Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let row_len = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Custom Staging Buffer"),
+        size: row_len as u64 * 8,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let view = texture.create_view(&Default::default());
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Custom Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations::default(),
+            })],
+            ..Default::default()
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, sim.current_bind_group(), &[]);
+        pass.draw(0..3, 0..1);
+    }
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(row_len),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    sim.queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::PollType::Wait).unwrap();
+    let data = slice.get_mapped_range();
+    let cells = data
+        .chunks(row_len as usize)
+        .flat_map(|row| row[..8 * 4].chunks(4).map(|pixel| (pixel[0] == 255) as u8))
+        .collect::<Vec<_>>();
+    assert_eq!(cells, GLIDER_2);
+}
+
+/// Checks that each cell of the `width` wide grid `cells` is drawn as a
+/// `scale` by `scale` square, starting at `offset`.
+#[track_caller]
+fn assert_image_eq(
+    image: &Image,
+    cells: &[u8],
+    width: usize,
+    offset: [u32; 2],
+    scale: u32,
+    palette: Palette,
+) {
+    for (index, &cell) in cells.iter().enumerate() {
+        let [x, y] = [(index % width) as u32, (index / width) as u32];
+        let expected = if cell != 0 {
+            palette.alive
+        } else {
+            palette.dead
+        };
+        for py in 0..scale {
+            for px in 0..scale {
+                let pixel = [offset[0] + x * scale + px, offset[1] + y * scale + py];
+                assert_eq!(
+                    image.pixel(pixel[0], pixel[1]),
+                    expected,
+                    "cell ({x}, {y}) at pixel {pixel:?}",
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,710 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use wgpu_gol::{
+    Bounds, Edges, Kernel, LifeSimulation, PackedGrid, ParseRuleError, Rule, SimError,
+    SimulationConfig, StatsOptions, Topology,
+};
+
+use common::{GLIDER_1, GLIDER_2, GLIDER_3, assert_grid_eq, copy_to_grid, do_step, do_steps};
+
+mod common;
+
+#[test]
+fn zero_steps() {
+    let gpu = common::require_gpu!();
+
+    const GRID_SIZE: usize = 8;
+
+    let all_on = [1; GRID_SIZE * GRID_SIZE];
+
+    let mut sim = gpu.simulation(GRID_SIZE as u32, GRID_SIZE as u32, &all_on);
+
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &state, &all_on);
+
+    let all_off = [0; GRID_SIZE * GRID_SIZE];
+    sim.reset_state(&all_off);
+
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &state, &all_off);
+}
+
+#[test]
+fn still_life() {
+    let gpu = common::require_gpu!();
+
+    const GRID_SIZE: usize = 8;
+
+    #[rustfmt::skip]
+    let init_state = [
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 1, 1, 0, 0, 0, 0, 0,
+        0, 1, 1, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 1, 1, 0,
+        0, 0, 0, 0, 1, 0, 1, 0,
+        0, 0, 0, 0, 1, 1, 0, 0,
+    ];
+
+    let mut sim = gpu.simulation(GRID_SIZE as u32, GRID_SIZE as u32, &init_state);
+
+    do_step(&mut sim);
+
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &init_state, &state);
+
+    do_step(&mut sim);
+
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &init_state, &state);
+}
+
+#[test]
+fn glider() {
+    let gpu = common::require_gpu!();
+
+    const GRID_SIZE: usize = 8;
+
+    let mut sim = gpu.simulation(GRID_SIZE as u32, GRID_SIZE as u32, GLIDER_1);
+
+    do_step(&mut sim);
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &state, GLIDER_2);
+
+    do_step(&mut sim);
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &state, GLIDER_3);
+}
+
+#[test]
+fn big_grid() {
+    let gpu = common::require_gpu!();
+
+    const GRID_SIZE: usize = 64;
+
+    let mut sim = gpu.simulation(
+        GRID_SIZE as u32,
+        GRID_SIZE as u32,
+        &[0; GRID_SIZE * GRID_SIZE],
+    );
+
+    for x_off in 0..GRID_SIZE - 8 {
+        for y_off in 0..GRID_SIZE - 8 {
+            eprintln!("Testing with offset ({}, {})", x_off, y_off);
+
+            // Initialize the full grid states by copying the smaller glider patterns into the full buffer.
+
+            let mut big_state_1 = [0u8; GRID_SIZE * GRID_SIZE];
+            copy_to_grid(GLIDER_1, &mut big_state_1, GRID_SIZE, [x_off, y_off]);
+
+            let mut big_state_2 = [0u8; GRID_SIZE * GRID_SIZE];
+            copy_to_grid(GLIDER_2, &mut big_state_2, GRID_SIZE, [x_off, y_off]);
+
+            let mut big_state_3 = [0u8; GRID_SIZE * GRID_SIZE];
+            copy_to_grid(GLIDER_3, &mut big_state_3, GRID_SIZE, [x_off, y_off]);
+
+            // Run the actual test.
+            sim.reset_state(&big_state_1);
+
+            do_step(&mut sim);
+
+            let state = sim.read_state();
+            assert_grid_eq(GRID_SIZE, &big_state_2, &state);
+
+            do_step(&mut sim);
+
+            let state = sim.read_state();
+            assert_grid_eq(GRID_SIZE, &big_state_3, &state);
+        }
+    }
+}
+
+#[test]
+fn parse_rules() {
+    assert_eq!("B3/S23".parse(), Ok(Rule::CONWAY));
+    assert_eq!("b3/s23".parse(), Ok(Rule::CONWAY));
+    assert_eq!("S23/B3".parse(), Ok(Rule::CONWAY));
+    assert_eq!("23/3".parse(), Ok(Rule::CONWAY));
+
+    assert_eq!("B36/S23".parse(), Ok(Rule::new(&[3, 6], &[2, 3])));
+    assert_eq!("B2/S".parse(), Ok(Rule::new(&[2], &[])));
+    assert_eq!(
+        "B3678/S34678".parse(),
+        Ok(Rule::new(&[3, 6, 7, 8], &[3, 4, 6, 7, 8])),
+    );
+
+    assert_eq!(
+        "B3S23".parse::<Rule>(),
+        Err(ParseRuleError::MissingSeparator)
+    );
+    assert_eq!("X3/S23".parse::<Rule>(), Err(ParseRuleError::InvalidPrefix));
+    assert_eq!(
+        "B39/S23".parse::<Rule>(),
+        Err(ParseRuleError::CountOutOfRange('9')),
+    );
+    assert_eq!(
+        "B3/S2,3".parse::<Rule>(),
+        Err(ParseRuleError::InvalidCharacter(',')),
+    );
+
+    for rule in ["B3/S23", "B36/S23", "B2/S", "B3678/S34678", "B/S012345678"] {
+        assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+    }
+}
+
+#[test]
+fn seeds() {
+    let gpu = common::require_gpu!();
+
+    const GRID_SIZE: usize = 8;
+
+    #[rustfmt::skip]
+    let init_state = [
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 1, 1, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    #[rustfmt::skip]
+    let expected = [
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 1, 1, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 1, 1, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let config = SimulationConfig {
+        rule: "B2/S".parse().unwrap(),
+        ..Default::default()
+    };
+    let mut sim =
+        gpu.simulation_with_config(GRID_SIZE as u32, GRID_SIZE as u32, &init_state, config);
+
+    do_step(&mut sim);
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, &expected, &state);
+}
+
+#[test]
+fn swap_rule() {
+    let gpu = common::require_gpu!();
+
+    const GRID_SIZE: usize = 8;
+
+    let mut sim = gpu.simulation(GRID_SIZE as u32, GRID_SIZE as u32, GLIDER_1);
+
+    do_step(&mut sim);
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, GLIDER_2, &state);
+
+    // Nothing is born and everything survives, so the glider is frozen in
+    // place.
+    sim.set_rule("B/S012345678".parse().unwrap());
+
+    do_step(&mut sim);
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, GLIDER_2, &state);
+
+    sim.set_rule(Rule::CONWAY);
+
+    do_step(&mut sim);
+    let state = sim.read_state();
+    assert_grid_eq(GRID_SIZE, GLIDER_3, &state);
+}
+
+#[test]
+fn rectangular_grid() {
+    let gpu = common::require_gpu!();
+
+    for [width, height] in [[40, 12], [12, 40], [70, 9], [33, 100]] {
+        eprintln!("Testing with {width}x{height} grid");
+
+        let num_cells = width * height;
+        let mut init_state = vec![0; num_cells];
+        copy_to_grid(GLIDER_1, &mut init_state, width, [width - 4, height - 4]);
+
+        let mut sim = gpu.simulation(width as u32, height as u32, &init_state);
+        assert_eq!(sim.logical_grid_size, [width as u32, height as u32]);
+        assert_eq!(sim.read_state(), init_state);
+
+        // The glider moves one cell down and to the right every 4 generations,
+        // so after 4 * 10 generations it has wrapped around both edges.
+        for _ in 0..4 * 10 {
+            do_step(&mut sim);
+        }
+
+        let mut expected = vec![0; num_cells];
+        copy_to_grid(GLIDER_1, &mut expected, width, [6, 6]);
+
+        let state = sim.read_state();
+        assert_grid_eq(width, &expected, &state);
+
+        // Resetting works with rectangular grids too.
+        sim.reset_state(&expected);
+        let state = sim.read_state();
+        assert_grid_eq(width, &expected, &state);
+    }
+}
+
+#[test]
+fn tiled_kernel() {
+    let gpu = common::require_gpu!();
+
+    let topologies = [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::KleinBottle {
+            twisted: Edges::Horizontal,
+            shift: 1,
+        },
+        Topology::CrossSurface,
+    ];
+
+    let mut rng = StdRng::seed_from_u64(5);
+    for [width, height] in [[8, 8], [33, 100], [300, 20], [70, 9], [64, 64]] {
+        for topology in topologies {
+            eprintln!("Testing tiled kernel with {width}x{height} {topology:?}");
+
+            let init_state = (0..width * height)
+                .map(|_| rng.random_range(0..2))
+                .collect::<Vec<u8>>();
+
+            let [mut direct, mut tiled] = [Kernel::Direct, Kernel::Tiled].map(|kernel| {
+                let config = SimulationConfig {
+                    topology,
+                    kernel,
+                    ..Default::default()
+                };
+                gpu.simulation_with_config(width as u32, height as u32, &init_state, config)
+            });
+
+            for _ in 0..10 {
+                do_step(&mut direct);
+                do_step(&mut tiled);
+            }
+
+            let expected = direct.read_state();
+            let state = tiled.read_state();
+            assert_grid_eq(width, &expected, &state);
+        }
+    }
+}
+
+#[test]
+fn temporal_kernel() {
+    let gpu = common::require_gpu!();
+
+    let topologies = [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::KleinBottle {
+            twisted: Edges::Vertical,
+            shift: 0,
+        },
+        Topology::CrossSurface,
+    ];
+
+    let mut rng = StdRng::seed_from_u64(6);
+    for [width, height] in [[8, 8], [33, 100], [300, 40], [70, 9]] {
+        for topology in topologies {
+            for generations in [1, 2, 5, Kernel::MAX_GENERATIONS] {
+                eprintln!(
+                    "Testing temporal kernel with {width}x{height} {topology:?}, \
+                    {generations} generations per pass",
+                );
+
+                let init_state = (0..width * height)
+                    .map(|_| rng.random_range(0..2))
+                    .collect::<Vec<u8>>();
+
+                let [mut direct, mut temporal] = [Kernel::Direct, Kernel::Temporal { generations }]
+                    .map(|kernel| {
+                        let config = SimulationConfig {
+                            topology,
+                            kernel,
+                            ..Default::default()
+                        };
+                        gpu.simulation_with_config(width as u32, height as u32, &init_state, config)
+                    });
+
+                // Run a number of generations that isn't a multiple of the
+                // generations per pass, so that the leftover generations are
+                // tested too.
+                let total = 2 * generations as u64 + 3;
+                for _ in 0..total {
+                    do_step(&mut direct);
+                }
+                do_steps(&mut temporal, total);
+
+                assert_eq!(temporal.step, total);
+
+                let expected = direct.read_state();
+                let state = temporal.read_state();
+                assert_grid_eq(width, &expected, &state);
+            }
+        }
+    }
+}
+
+#[test]
+fn invalid_config() {
+    fn try_new(width: u32, height: u32, state: &[u8], config: SimulationConfig) -> SimError {
+        match pollster::block_on(LifeSimulation::try_new(width, height, state, config)) {
+            Ok(_) => panic!("Expected a {width}x{height} simulation to fail"),
+            Err(err) => err,
+        }
+    }
+
+    let err = try_new(0, 8, &[], SimulationConfig::default());
+    assert!(matches!(
+        err,
+        SimError::InvalidDimensions {
+            width: 0,
+            height: 8,
+        },
+    ));
+
+    let err = try_new(1 << 16, 1 << 16, &[], SimulationConfig::default());
+    assert!(matches!(err, SimError::InvalidDimensions { .. }));
+
+    let err = try_new(8, 8, &[0; 63], SimulationConfig::default());
+    assert!(matches!(
+        err,
+        SimError::InvalidStateLength {
+            expected: 64,
+            actual: 63,
+        },
+    ));
+
+    for generations in [0, Kernel::MAX_GENERATIONS + 1] {
+        let config = SimulationConfig {
+            kernel: Kernel::Temporal { generations },
+            ..Default::default()
+        };
+        let err = try_new(8, 8, &[0; 64], config);
+        assert!(matches!(err, SimError::InvalidGenerations(g) if g == generations));
+    }
+}
+
+#[test]
+fn limit_exceeded() {
+    let gpu = common::require_gpu!();
+
+    // The largest grid with a `u32` cell count is still far larger than the
+    // state buffers the device allows.
+    let grid = PackedGrid::new(65535, 65535);
+    let result = pollster::block_on(LifeSimulation::from_device_with_grid(
+        &gpu.device,
+        &gpu.queue,
+        &grid,
+        SimulationConfig::default(),
+    ));
+    let state_size = 2048 * 65535 * 4;
+    assert!(matches!(
+        result,
+        Err(SimError::LimitExceeded { requested, .. }) if requested == state_size,
+    ));
+}
+
+#[test]
+fn config_builder() {
+    let gpu = common::require_gpu!();
+
+    let config = SimulationConfig::new()
+        .rule(Rule::new(&[3, 6], &[2, 3]))
+        .topology(Topology::Plane)
+        .kernel(Kernel::Tiled)
+        .power_preference(wgpu::PowerPreference::LowPower);
+    assert_eq!(config.rule, Rule::new(&[3, 6], &[2, 3]));
+    assert_eq!(config.topology, Topology::Plane);
+    assert_eq!(config.kernel, Kernel::Tiled);
+    assert_eq!(config.power_preference, wgpu::PowerPreference::LowPower);
+    assert!(!config.force_fallback_adapter);
+
+    // A glider on a plane built from the config still flies.
+    let mut init_state = [0; 16 * 16];
+    copy_to_grid(GLIDER_1, &mut init_state, 16, [0, 0]);
+    let mut sim = gpu.simulation_with_config(16, 16, &init_state, config);
+    do_steps(&mut sim, 4);
+
+    let mut expected = [0; 16 * 16];
+    copy_to_grid(GLIDER_1, &mut expected, 16, [1, 1]);
+    assert_grid_eq(16, &expected, &sim.read_state());
+
+    // No device supports unlimited workgroups, so requesting it fails cleanly.
+    let config = common::adapter_config().required_limits(wgpu::Limits {
+        max_compute_workgroups_per_dimension: u32::MAX,
+        ..Default::default()
+    });
+    let result = pollster::block_on(LifeSimulation::try_new(8, 8, &[0; 64], config));
+    assert!(matches!(result, Err(SimError::RequestDevice(_))));
+}
+
+#[test]
+fn shared_device() {
+    let gpu = common::require_gpu!();
+
+    // Two simulations on the same device don't interfere with each other.
+    let mut init_state = [0; 8 * 8];
+    copy_to_grid(GLIDER_1, &mut init_state, 8, [0, 0]);
+    let mut glider = pollster::block_on(LifeSimulation::from_device(
+        &gpu.device,
+        &gpu.queue,
+        8,
+        8,
+        &init_state,
+        SimulationConfig::default(),
+    ))
+    .unwrap();
+    let mut empty = pollster::block_on(LifeSimulation::from_device(
+        &gpu.device,
+        &gpu.queue,
+        8,
+        8,
+        &[0; 8 * 8],
+        SimulationConfig::default(),
+    ))
+    .unwrap();
+
+    // Only simulations that request their own device know its adapter.
+    assert!(glider.instance.is_none() && glider.adapter.is_none());
+    let own_device = pollster::block_on(LifeSimulation::try_new(
+        8,
+        8,
+        &init_state,
+        common::adapter_config(),
+    ))
+    .unwrap();
+    assert!(own_device.instance.is_some() && own_device.adapter.is_some());
+
+    do_step(&mut glider);
+    do_step(&mut empty);
+    assert_grid_eq(8, GLIDER_2, &glider.read_state());
+    assert_grid_eq(8, &[0; 8 * 8], &empty.read_state());
+
+    // The state buffers live on the caller's device, and are big enough for
+    // one block per row of the 8x8 grid.
+    assert_eq!(glider.device, gpu.device);
+    assert_eq!(glider.current_state_buffer().size(), 8 * 4);
+}
+
+#[test]
+fn async_readback() {
+    let gpu = common::require_gpu!();
+
+    let mut init_state = [0; 8 * 8];
+    copy_to_grid(GLIDER_1, &mut init_state, 8, [0, 0]);
+    let mut sim = gpu.simulation(8, 8, &init_state);
+
+    // Start a read after each step without waiting for any of them, so that
+    // several staging buffers are in flight at once.
+    let mut readbacks = Vec::new();
+    for _ in 0..3 {
+        do_step(&mut sim);
+        readbacks.push(sim.read_state_async());
+    }
+
+    // A read that's dropped before it completes frees its staging buffer.
+    do_step(&mut sim);
+    drop(sim.read_state_async());
+
+    sim.device.poll(wgpu::PollType::Wait).unwrap();
+    let states = readbacks
+        .into_iter()
+        .map(|readback| pollster::block_on(readback).unwrap())
+        .collect::<Vec<_>>();
+    assert_grid_eq(8, GLIDER_2, &states[0]);
+    assert_grid_eq(8, GLIDER_3, &states[1]);
+
+    let mut expected = [0; 8 * 8];
+    copy_to_grid(GLIDER_1, &mut expected, 8, [1, 1]);
+    assert_grid_eq(8, &expected, &sim.read_state());
+    assert_ne!(states[2], expected);
+
+    // The read buffer can still be filled and mapped directly.
+    let mut encoder = sim
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    sim.encode_read(&mut encoder);
+    sim.queue.submit([encoder.finish()]);
+    let slice = sim.read_buf.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    sim.device.poll(wgpu::PollType::Wait).unwrap();
+    let blocks = bytemuck::cast_slice::<u8, u32>(&slice.get_mapped_range()).to_vec();
+    sim.read_buf.unmap();
+    assert_eq!(PackedGrid::from_blocks(8, 8, blocks), sim.read_grid());
+}
+
+#[test]
+fn regions() {
+    let gpu = common::require_gpu!();
+
+    let [width, height] = [100, 70];
+    let mut rng = StdRng::seed_from_u64(12);
+    let init_state = (0..width * height)
+        .map(|_| rng.random_range(0..2))
+        .collect::<Vec<u8>>();
+    let mut sim = gpu.simulation(width as u32, height as u32, &init_state);
+    do_step(&mut sim);
+    let mut expected = sim.read_state();
+
+    // Regions aligned to blocks, straddling block boundaries, and touching the
+    // edges of the grid.
+    let regions = [
+        [0, 0, 32, 5],
+        [30, 10, 8, 8],
+        [3, 60, 90, 10],
+        [64, 0, 36, 70],
+        [99, 69, 1, 1],
+        [0, 0, 100, 70],
+    ];
+
+    for [x, y, w, h] in regions {
+        let region = sim.read_region(x as u32, y as u32, w as u32, h as u32);
+        for row in 0..h {
+            let start = (y + row) * width + x;
+            assert_eq!(
+                &region[row * w..(row + 1) * w],
+                &expected[start..start + w],
+                "Region {w}x{h} at ({x}, {y}) differs in row {row}",
+            );
+        }
+    }
+
+    // Stamp random cells into each region and check that nothing outside of
+    // it changed.
+    for [x, y, w, h] in regions {
+        let stamp = (0..w * h)
+            .map(|_| rng.random_range(0..2))
+            .collect::<Vec<u8>>();
+        sim.write_region(x as u32, y as u32, w as u32, h as u32, &stamp);
+
+        for row in 0..h {
+            let start = (y + row) * width + x;
+            expected[start..start + w].copy_from_slice(&stamp[row * w..(row + 1) * w]);
+        }
+        assert_grid_eq(width, &expected, &sim.read_state());
+    }
+}
+
+#[test]
+fn stats() {
+    let gpu = common::require_gpu!();
+
+    // An empty grid has no bounding box.
+    let mut sim = gpu.simulation(8, 8, &[0; 8 * 8]);
+    let stats = sim.read_stats(StatsOptions::new());
+    assert_eq!(stats.population, 0);
+    assert_eq!(stats.bounds, None);
+    assert_eq!(stats.row_counts, None);
+
+    let mut init_state = [0; 8 * 8];
+    copy_to_grid(GLIDER_1, &mut init_state, 8, [3, 4]);
+    sim.reset_state(&init_state);
+    assert_eq!(sim.population(), 5);
+    let stats = sim.read_stats(StatsOptions::new().row_counts(true).column_counts(true));
+    assert_eq!(
+        stats.bounds,
+        Some(Bounds {
+            x: 3,
+            y: 4,
+            width: 3,
+            height: 3,
+        }),
+    );
+    assert_eq!(stats.row_counts.unwrap(), [0, 0, 0, 0, 1, 2, 2, 0]);
+    assert_eq!(stats.column_counts.unwrap(), [0, 0, 0, 1, 1, 3, 0, 0]);
+
+    // Compare against the CPU on random grids spanning several blocks and
+    // several workgroups, with and without partial blocks at the end of each
+    // row.
+    let mut rng = StdRng::seed_from_u64(14);
+    for [width, height] in [[100, 70], [64, 200], [33, 1], [1000, 9]] {
+        let mut grid = PackedGrid::new(width, height);
+        for _ in 0..rng.random_range(1..50) {
+            let [x, y] = [rng.random_range(0..width), rng.random_range(0..height)];
+            grid.set(x, y, true);
+        }
+
+        let mut sim = gpu
+            .try_simulation_from_grid(&grid, SimulationConfig::default())
+            .unwrap();
+
+        for _ in 0..3 {
+            let cells = sim.read_grid();
+            let stats = sim.read_stats(StatsOptions::new().row_counts(true).column_counts(true));
+            assert_eq!(stats.population, cells.count());
+            assert_eq!(stats.bounds, cells.bounds());
+
+            let mut row_counts = vec![0; height as usize];
+            let mut column_counts = vec![0; width as usize];
+            for [x, y] in cells.live_cells() {
+                row_counts[y as usize] += 1;
+                column_counts[x as usize] += 1;
+            }
+            assert_eq!(stats.row_counts.unwrap(), row_counts);
+            assert_eq!(stats.column_counts.unwrap(), column_counts);
+
+            do_step(&mut sim);
+        }
+    }
+
+    // Several reads can be in flight at once, each seeing the generation it
+    // was started on.
+    let mut sim = gpu.simulation(8, 8, &init_state);
+    let first = sim.read_stats_async(StatsOptions::new());
+    do_steps(&mut sim, 4);
+    let second = sim.read_stats_async(StatsOptions::new());
+    sim.device.poll(wgpu::PollType::Wait).unwrap();
+    let first = pollster::block_on(first).unwrap();
+    let second = pollster::block_on(second).unwrap();
+    assert_eq!(first.bounds.unwrap().x, 3);
+    assert_eq!(second.bounds.unwrap().x, 4);
+
+    // Stats can also be recorded in between steps in one encoder, and read
+    // back once it has been submitted.
+    let mut sim = gpu.simulation(8, 8, &init_state);
+    let mut encoder = sim
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    let mut encoded = Vec::new();
+    for _ in 0..3 {
+        encoded.push(sim.encode_stats(&mut encoder, StatsOptions::new()));
+        sim.encode_steps(&mut encoder, 4);
+    }
+    sim.queue.submit([encoder.finish()]);
+
+    let readbacks = encoded
+        .into_iter()
+        .map(|encoded| encoded.submitted())
+        .collect::<Vec<_>>();
+    sim.device.poll(wgpu::PollType::Wait).unwrap();
+    let bounds = readbacks
+        .into_iter()
+        .map(|readback| pollster::block_on(readback).unwrap().bounds.unwrap().x)
+        .collect::<Vec<_>>();
+    assert_eq!(bounds, [3, 4, 5]);
+
+    // Reads that are never started don't hand their staging buffers to later
+    // reads, since the encoder they were recorded into can still be submitted
+    // while the later reads are in flight.
+    let sim = gpu.simulation(8, 8, &init_state);
+    let mut encoder = sim
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    drop(sim.encode_stats(&mut encoder, StatsOptions::new()));
+    let readback = sim.read_stats_async(StatsOptions::new());
+    sim.queue.submit([encoder.finish()]);
+    sim.device.poll(wgpu::PollType::Wait).unwrap();
+    assert_eq!(pollster::block_on(readback).unwrap().population, 5);
+    assert_eq!(sim.read_stats(StatsOptions::new()).population, 5);
+}
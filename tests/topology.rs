@@ -0,0 +1,261 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use wgpu_gol::{Edges, ParseTopologyError, SimulationConfig, Topology};
+
+use common::{GLIDER_1, Gpu, assert_grid_eq, copy_to_grid, do_step};
+
+mod common;
+
+#[test]
+fn resolve_topology() {
+    let grid_size = [10, 8];
+
+    // Cells inside the grid are unaffected by the topology.
+    for topology in [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::CrossSurface,
+        Topology::KleinBottle {
+            twisted: Edges::Horizontal,
+            shift: 1,
+        },
+    ] {
+        assert_eq!(topology.resolve(grid_size, 3, 4), Some([3, 4]));
+    }
+
+    assert_eq!(Topology::Torus.resolve(grid_size, -1, 4), Some([9, 4]));
+    assert_eq!(Topology::Torus.resolve(grid_size, 3, 8), Some([3, 0]));
+    assert_eq!(Topology::Torus.resolve(grid_size, -1, -1), Some([9, 7]));
+
+    assert_eq!(Topology::Plane.resolve(grid_size, -1, 4), None);
+    assert_eq!(Topology::Plane.resolve(grid_size, 3, 8), None);
+
+    let klein = Topology::KleinBottle {
+        twisted: Edges::Horizontal,
+        shift: 0,
+    };
+    assert_eq!(klein.resolve(grid_size, -1, 4), Some([9, 4]));
+    assert_eq!(klein.resolve(grid_size, 3, 8), Some([6, 0]));
+    assert_eq!(klein.resolve(grid_size, 3, -1), Some([6, 7]));
+
+    let shifted_klein = Topology::KleinBottle {
+        twisted: Edges::Vertical,
+        shift: 1,
+    };
+    assert_eq!(shifted_klein.resolve(grid_size, 3, 8), Some([3, 0]));
+    assert_eq!(shifted_klein.resolve(grid_size, -1, 4), Some([9, 4]));
+    assert_eq!(shifted_klein.resolve(grid_size, 10, 0), Some([0, 0]));
+
+    assert_eq!(
+        Topology::CrossSurface.resolve(grid_size, -1, 4),
+        Some([9, 3])
+    );
+    assert_eq!(
+        Topology::CrossSurface.resolve(grid_size, 3, 8),
+        Some([6, 0])
+    );
+    assert_eq!(
+        Topology::CrossSurface.resolve(grid_size, -1, -1),
+        Some([0, 0])
+    );
+}
+
+/// Runs a glider for `steps` generations with the given topology.
+fn run_glider(
+    gpu: &Gpu,
+    topology: Topology,
+    grid_size: [usize; 2],
+    offset: [usize; 2],
+    steps: usize,
+) -> Vec<u8> {
+    let [width, height] = grid_size;
+
+    let mut init_state = vec![0; width * height];
+    copy_to_grid(GLIDER_1, &mut init_state, width, offset);
+
+    let config = SimulationConfig {
+        topology,
+        ..Default::default()
+    };
+    let mut sim = gpu.simulation_with_config(width as u32, height as u32, &init_state, config);
+
+    for _ in 0..steps {
+        do_step(&mut sim);
+    }
+
+    sim.read_state()
+}
+
+#[test]
+fn torus_topology() {
+    let gpu = common::require_gpu!();
+
+    const WIDTH: usize = 50;
+    const HEIGHT: usize = 30;
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let init_state = (0..WIDTH * HEIGHT)
+        .map(|_| rng.random_range(0..2))
+        .collect::<Vec<u8>>();
+
+    let mut default_sim = gpu.simulation(WIDTH as u32, HEIGHT as u32, &init_state);
+
+    let config = SimulationConfig {
+        topology: Topology::Torus,
+        ..Default::default()
+    };
+    let mut torus_sim =
+        gpu.simulation_with_config(WIDTH as u32, HEIGHT as u32, &init_state, config);
+
+    for _ in 0..20 {
+        do_step(&mut default_sim);
+        do_step(&mut torus_sim);
+
+        let expected = default_sim.read_state();
+        let state = torus_sim.read_state();
+        assert_grid_eq(WIDTH, &expected, &state);
+    }
+}
+
+#[test]
+fn plane_topology() {
+    let gpu = common::require_gpu!();
+
+    // The glider crashes into the corner of the grid and turns into a block.
+    #[rustfmt::skip]
+    let expected = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1,
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1,
+    ];
+
+    let state = run_glider(gpu, Topology::Plane, [10, 8], [4, 3], 24);
+    assert_grid_eq(10, &expected, &state);
+}
+
+#[test]
+fn klein_bottle_topology() {
+    let gpu = common::require_gpu!();
+
+    // The glider crosses the bottom edge and comes back through the top edge
+    // mirrored.
+    #[rustfmt::skip]
+    let expected = [
+        0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 0, 0,
+    ];
+
+    let topology = Topology::KleinBottle {
+        twisted: Edges::Horizontal,
+        shift: 0,
+    };
+    let state = run_glider(gpu, topology, [12, 10], [3, 5], 12);
+    assert_grid_eq(12, &expected, &state);
+
+    // Same thing, but crossing the right edge with a shift.
+    #[rustfmt::skip]
+    let expected = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let topology = Topology::KleinBottle {
+        twisted: Edges::Vertical,
+        shift: 1,
+    };
+    let state = run_glider(gpu, topology, [10, 12], [5, 3], 12);
+    assert_grid_eq(10, &expected, &state);
+}
+
+#[test]
+fn cross_surface_topology() {
+    let gpu = common::require_gpu!();
+
+    #[rustfmt::skip]
+    let expected = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let state = run_glider(gpu, Topology::CrossSurface, [12, 10], [7, 2], 10);
+    assert_grid_eq(12, &expected, &state);
+}
+
+#[test]
+fn parse_topology() {
+    for topology in [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::CrossSurface,
+        Topology::KleinBottle {
+            twisted: Edges::Horizontal,
+            shift: 0,
+        },
+        Topology::KleinBottle {
+            twisted: Edges::Vertical,
+            shift: 3,
+        },
+    ] {
+        assert_eq!(topology.to_string().parse(), Ok(topology));
+    }
+
+    assert_eq!("Torus".parse(), Ok(Topology::Torus));
+    assert_eq!(
+        "klein-bottle+2".parse(),
+        Ok(Topology::KleinBottle {
+            twisted: Edges::Horizontal,
+            shift: 2,
+        }),
+    );
+    assert_eq!(
+        Topology::KleinBottle {
+            twisted: Edges::Vertical,
+            shift: 3,
+        }
+        .to_string(),
+        "klein-bottle-vertical+3",
+    );
+
+    assert_eq!(
+        "sphere".parse::<Topology>(),
+        Err(ParseTopologyError::UnknownTopology("sphere".to_owned())),
+    );
+    assert_eq!(
+        "klein-bottle+x".parse::<Topology>(),
+        Err(ParseTopologyError::InvalidShift("x".to_owned())),
+    );
+    assert_eq!(
+        "torus+1".parse::<Topology>(),
+        Err(ParseTopologyError::UnexpectedShift),
+    );
+}